@@ -0,0 +1,107 @@
+use crate::{interval::{self, Interval}, ray::Ray, vector::Vec3D};
+
+pub const EMPTY: Aabb = Aabb::new(interval::EMPTY, interval::EMPTY, interval::EMPTY);
+pub const UNIVERSE: Aabb = Aabb::new(interval::UNIVERSE, interval::UNIVERSE, interval::UNIVERSE);
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Box spanned by two opposite corners, in any order.
+    pub fn from_points(a: &Vec3D, b: &Vec3D) -> Self {
+        Self {
+            x: Interval::new(a.x.min(b.x), a.x.max(b.x)),
+            y: Interval::new(a.y.min(b.y), a.y.max(b.y)),
+            z: Interval::new(a.z.min(b.z), a.z.max(b.z)),
+        }.pad()
+    }
+
+    /// Smallest box containing both 'a' and 'b'.
+    pub fn enclosing(a: &Aabb, b: &Aabb) -> Self {
+        Self {
+            x: Interval::enclosing(&a.x, &b.x),
+            y: Interval::enclosing(&a.y, &b.y),
+            z: Interval::enclosing(&a.z, &b.z),
+        }
+    }
+
+    /// Grows flat sides slightly so rays never miss a box of zero thickness.
+    pub fn pad(&self) -> Self {
+        let delta = 0.0001;
+        let pad = |i: Interval| if i.size() >= delta { i } else { i.expand(delta) };
+
+        Self { x: pad(self.x), y: pad(self.y), z: pad(self.z) }
+    }
+
+    pub fn axis(&self, n: usize) -> &Interval {
+        match n {
+            0 => &self.x,
+            1 => &self.y,
+            _ => &self.z,
+        }
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() { 0 } else { 2 }
+        } else if self.y.size() > self.z.size() { 1 } else { 2 }
+    }
+
+    pub fn centroid(&self) -> Vec3D {
+        Vec3D::new(
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let (dx, dy, dz) = (self.x.size().max(0.0), self.y.size().max(0.0), self.z.size().max(0.0));
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z].iter().all(|i| i.min.is_finite() && i.max.is_finite())
+    }
+
+    pub fn hit(&self, ray: &Ray, t: Interval) -> bool {
+        self.clip(ray, t).is_some()
+    }
+
+    /// The part of 't' where 'ray' is inside the box, if any.
+    pub fn clip(&self, ray: &Ray, t: Interval) -> Option<Interval> {
+        let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+        let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+
+        let mut t_min = t.min;
+        let mut t_max = t.max;
+
+        for n in 0..3 {
+            let axis = self.axis(n);
+            let inverse_direction = 1.0 / direction[n];
+
+            let t0 = (axis.min - origin[n]) * inverse_direction;
+            let t1 = (axis.max - origin[n]) * inverse_direction;
+
+            let (t0, t1) = if inverse_direction < 0.0 { (t1, t0) } else { (t0, t1) };
+
+            if t0 > t_min { t_min = t0 }
+            if t1 < t_max { t_max = t1 }
+
+            if t_max <= t_min {
+                return None
+            }
+        }
+
+        Some(Interval::new(t_min, t_max))
+    }
+}
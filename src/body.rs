@@ -1,17 +1,32 @@
 pub mod body_list;
 pub mod bodies;
+pub mod bvh;
+pub mod csg;
+pub mod instance;
+pub mod medium;
+pub mod mesh;
+pub mod motion;
+pub mod sdf;
+pub mod volume;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::{vector::Vec3D, ray::Ray, interval::Interval, material::{Material, materials::Base}};
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::Aabb, material::{Material, materials::Base}};
+
+use body_list::BodyList;
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub point: Vec3D,
     pub normal: Vec3D,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
     pub t: f32,
     pub front_face: bool,
+    /// Surface coordinates, interpolated from texture coordinates where available.
+    pub u: f32,
+    pub v: f32,
+    /// Weights of the three vertices when a triangle was hit.
+    pub barycentric: Vec3D,
 }
 
 impl HitRecord {
@@ -19,12 +34,20 @@ impl HitRecord {
         Self {
             point: Vec3D::zero(),
             normal: Vec3D::zero(),
-            material: Rc::new(Base {}),
+            material: Arc::new(Base {}),
             t: 0.0,
             front_face: false,
+            u: 0.0,
+            v: 0.0,
+            barycentric: Vec3D::zero(),
         }
     }
 
+    /// Marks an open end of a 'Span' at infinity, where no surface was hit.
+    pub fn unbounded(t: f32) -> Self {
+        Self { t, ..Self::new() }
+    }
+
     /// 'outward_normal' must be normalized.
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3D) {
         self.front_face = ray.direction.dot(outward_normal) < 0.0;
@@ -32,6 +55,99 @@ impl HitRecord {
     }
 }
 
-pub trait Body {
+/// Stretch of a ray inside a body, from the hit where it enters to the one
+/// where it leaves. Spans reaching past the interval they were found in have
+/// an entry at negative or an exit at positive infinity.
+#[derive(Clone)]
+pub struct Span {
+    pub entry: HitRecord,
+    pub exit: HitRecord,
+}
+
+impl Span {
+    /// Drops the spans starting after 't_max' and opens those ending after it.
+    pub fn clip(spans: Vec<Span>, t_max: f32) -> Vec<Span> {
+        spans.into_iter()
+            .filter(|span| span.entry.t <= t_max)
+            .map(|mut span| {
+                if span.exit.t > t_max {
+                    span.exit = HitRecord::unbounded(f32::INFINITY);
+                }
+                span
+            })
+            .collect()
+    }
+}
+
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait Body: Send + Sync {
     fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool;
-}
\ No newline at end of file
+
+    /// Box enclosing the whole body. Unbounded bodies return 'aabb::UNIVERSE'.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Stretches of 'ray' within 't' that lie inside the body, in order.
+    /// Front faces count as entering and back faces as leaving, so this only
+    /// makes sense for closed bodies and half-spaces like 'Plane'.
+    fn spans(&self, ray: &Ray, t: Interval) -> Vec<Span> {
+        let mut spans = vec![];
+        let mut entry = None;
+        let mut depth = 0;
+        let mut hit_record = HitRecord::new();
+        let mut t_min = t.min;
+
+        // Searched past 't.max', since a ray can only be known to be inside from where it leaves.
+        while self.hit(ray, Interval::new(t_min, f32::INFINITY), &mut hit_record) {
+            if hit_record.front_face {
+                if depth == 0 {
+                    entry = Some(hit_record.clone());
+                }
+                depth += 1;
+            } else if depth <= 1 {
+                // Leaving without having entered means the ray started inside.
+                let entry = entry.take().unwrap_or_else(|| HitRecord::unbounded(f32::NEG_INFINITY));
+                spans.push(Span { entry, exit: hit_record.clone() });
+                depth = 0;
+            } else {
+                depth -= 1;
+            }
+
+            // Past the hit, so faces meeting at an edge are only crossed once.
+            t_min = hit_record.t + (hit_record.t.abs() * 1e-6).max(1e-6);
+        }
+
+        if let Some(entry) = entry {
+            spans.push(Span { entry, exit: HitRecord::unbounded(f32::INFINITY) });
+        }
+
+        Span::clip(spans, t.max)
+    }
+
+    /// Fraction of light getting through along 'ray' within 't', for shadow
+    /// rays. Media estimate it without picking a place to scatter, anything
+    /// else blocks all light wherever 'hit' finds it.
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        if self.hit(ray, t, &mut HitRecord::new()) { 0.0 } else { 1.0 }
+    }
+
+    /// Density, per unit solid angle seen from 'origin', of 'random_direction'
+    /// returning 'direction'. Zero for bodies that cannot be sampled.
+    fn pdf_value(&self, _origin: &Vec3D, _direction: &Vec3D) -> f32 {
+        0.0
+    }
+
+    /// Direction from 'origin' towards a random point of the body, so it can be
+    /// sampled as a light. Only meaningful where 'pdf_value' is implemented.
+    fn random_direction(&self, _origin: &Vec3D) -> Vec3D {
+        Vec3D::x_unit()
+    }
+
+    /// Adds this body to 'lights' if it emits light and can be sampled, or
+    /// searches its children if it has any.
+    fn collect_lights(self: Arc<Self>, _lights: &mut BodyList) {}
+}
@@ -1,13 +1,24 @@
-use std::{rc::Rc, f32};
+use std::{sync::Arc, f32};
 
-use crate::{vector::Vec3D, interval::Interval, material::Material};
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::{self, Aabb}, material::Material, random};
 
-use super::{Body, HitRecord};
+use super::{Body, HitRecord, Span, body_list::BodyList};
 
 pub struct Sphere {
     pub center: Vec3D,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
+}
+
+impl Sphere {
+    /// Maps a point on the unit sphere to 'u' around the y axis starting at -x,
+    /// and 'v' from the bottom pole to the top.
+    pub fn uv(point: &Vec3D) -> (f32, f32) {
+        let theta = (-point.y).clamp(-1.0, 1.0).acos();
+        let phi = (-point.z).atan2(point.x) + f32::consts::PI;
+
+        (phi / (2.0 * f32::consts::PI), theta / f32::consts::PI)
+    }
 }
 
 impl Body for Sphere {
@@ -38,15 +49,65 @@ impl Body for Sphere {
         let outward_normal = (hit_record.point - self.center) / self.radius;
         hit_record.set_face_normal(ray, &outward_normal);
         hit_record.material = self.material.clone();
+        (hit_record.u, hit_record.v) = Self::uv(&outward_normal);
         
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3D::one() * self.radius;
+        Aabb::from_points(&(self.center - radius), &(self.center + radius))
+    }
+
+    /// Uniform over the cone of directions the sphere covers, or over all
+    /// directions from inside it.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let mut hit_record = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f32::INFINITY), &mut hit_record) {
+            return 0.0
+        }
+
+        let distance2 = (self.center - *origin).mag2();
+        if distance2 <= self.radius.powi(2) {
+            return 1.0 / (4.0 * f32::consts::PI)
+        }
+
+        let solid_angle = 2.0 * f32::consts::PI * one_minus_cos_theta_max(self.radius.powi(2) / distance2);
+
+        1.0 / solid_angle
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        let direction = self.center - *origin;
+        let distance2 = direction.mag2();
+        if distance2 <= self.radius.powi(2) {
+            return Vec3D::random_unit()
+        }
+
+        // Works with '1 - z' rather than through 'random_in_cone', so directions
+        // towards far away spheres don't all collapse onto 'w'.
+        let one_minus_z = random() * one_minus_cos_theta_max(self.radius.powi(2) / distance2);
+        let z = 1.0 - one_minus_z;
+        let phi = 2.0 * f32::consts::PI * random();
+        let sin_theta = (one_minus_z * (2.0 - one_minus_z)).max(0.0).sqrt();
+
+        let w = direction.unit();
+        let (u, v) = w.orthonormal_basis();
+
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + z * w
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
 }
 
 pub struct Plane {
     pub center: Vec3D,
     pub normal: Vec3D,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl Body for Plane {
@@ -68,6 +129,987 @@ impl Body for Plane {
         hit_record.set_face_normal(ray, &self.normal);
         hit_record.material = self.material.clone();
 
+        // One texture repeat per unit of distance along the plane.
+        let (tangent, bitangent) = self.normal.unit().orthonormal_basis();
+        let offset = hit_record.point - self.center;
+        hit_record.u = offset.dot(&tangent);
+        hit_record.v = offset.dot(&bitangent);
+
         true
     }
-}
\ No newline at end of file
+
+    fn bounding_box(&self) -> Aabb {
+        aabb::UNIVERSE
+    }
+
+    /// The half-space behind the plane, which rays can be inside of without
+    /// ever crossing it.
+    fn spans(&self, ray: &Ray, t: Interval) -> Vec<Span> {
+        let mut hit_record = HitRecord::new();
+        let crosses = self.hit(ray, t, &mut hit_record);
+        let behind = |point: Vec3D| (point - self.center).dot(&self.normal) < 0.0;
+
+        let span = match (crosses, hit_record.front_face) {
+            (true, true) => Span { entry: hit_record, exit: HitRecord::unbounded(f32::INFINITY) },
+            (true, false) => Span { entry: HitRecord::unbounded(f32::NEG_INFINITY), exit: hit_record },
+            (false, _) if behind(ray.at(t.min)) => Span {
+                entry: HitRecord::unbounded(f32::NEG_INFINITY),
+                exit: HitRecord::unbounded(f32::INFINITY),
+            },
+            (false, _) => return vec![],
+        };
+
+        vec![span]
+    }
+}
+
+pub struct Triangle {
+    pub a: Vec3D,
+    pub b: Vec3D,
+    pub c: Vec3D,
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Möller–Trumbore intersection. Returns the ray parameter and the
+    /// barycentric weights of 'b' and 'c'.
+    pub fn intersect(a: &Vec3D, b: &Vec3D, c: &Vec3D, ray: &Ray, t: &Interval) -> Option<(f32, f32, f32)> {
+        let edge_1 = *b - *a;
+        let edge_2 = *c - *a;
+
+        let p = ray.direction.cross(&edge_2);
+        let determinant = edge_1.dot(&p);
+
+        if determinant.abs() < f32::EPSILON {
+            return None
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let s = ray.origin - *a;
+
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None
+        }
+
+        let q = s.cross(&edge_1);
+
+        let v = ray.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None
+        }
+
+        let root = edge_2.dot(&q) * inverse_determinant;
+        if t.surrounds(root) {
+            return None
+        }
+
+        Some((root, u, v))
+    }
+}
+
+impl Body for Triangle {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some((root, u, v)) = Self::intersect(&self.a, &self.b, &self.c, ray, &t) else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        let outward_normal = (self.b - self.a).cross(&(self.c - self.a)).unit();
+        hit_record.set_face_normal(ray, &outward_normal);
+        hit_record.material = self.material.clone();
+        hit_record.u = u;
+        hit_record.v = v;
+        hit_record.barycentric = Vec3D::new(1.0 - u - v, u, v);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::enclosing(&Aabb::from_points(&self.a, &self.b), &Aabb::from_points(&self.c, &self.c))
+    }
+
+    /// Uniform over the area of the triangle, converted to solid angle.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let ray = Ray::new(origin, direction);
+        let Some((root, _, _)) = Self::intersect(&self.a, &self.b, &self.c, &ray, &Interval::new(0.001, f32::INFINITY)) else {
+            return 0.0
+        };
+
+        let normal = (self.b - self.a).cross(&(self.c - self.a));
+        let area = 0.5 * normal.mag();
+
+        area_to_solid_angle(1.0 / area, root * direction.mag(), normal.unit().dot(&direction.unit()))
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        random_point_in_triangle(&self.a, &self.b, &self.c) - *origin
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// Parallelogram spanned by the edges 'u' and 'v' from 'corner'. The front
+/// face is the side 'u × v' points to.
+pub struct Quad {
+    pub corner: Vec3D,
+    pub u: Vec3D,
+    pub v: Vec3D,
+    pub material: Arc<dyn Material>,
+}
+
+impl Quad {
+    /// Returns the ray parameter and the position of the hit along 'u' and 'v', both in [0, 1].
+    pub fn intersect(&self, ray: &Ray, t: &Interval) -> Option<(f32, f32, f32)> {
+        let normal = self.u.cross(&self.v);
+        let denom = normal.unit().dot(&ray.direction);
+
+        if denom.abs() < f32::EPSILON {
+            return None
+        }
+
+        let root = (self.corner - ray.origin).dot(&normal.unit()) / denom;
+        if t.surrounds(root) {
+            return None
+        }
+
+        // Coordinates of the hit in the basis of the edges.
+        let offset = ray.at(root) - self.corner;
+        let w = normal / normal.mag2();
+        let alpha = w.dot(&offset.cross(&self.v));
+        let beta = w.dot(&self.u.cross(&offset));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None
+        }
+
+        Some((root, alpha, beta))
+    }
+}
+
+impl Body for Quad {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some((root, u, v)) = self.intersect(ray, &t) else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        hit_record.set_face_normal(ray, &self.u.cross(&self.v).unit());
+        hit_record.material = self.material.clone();
+        hit_record.u = u;
+        hit_record.v = v;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::enclosing(
+            &Aabb::from_points(&self.corner, &(self.corner + self.u + self.v)),
+            &Aabb::from_points(&(self.corner + self.u), &(self.corner + self.v)),
+        )
+    }
+
+    /// Uniform over the area of the quad, converted to solid angle.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let Some((root, _, _)) = self.intersect(&Ray::new(origin, direction), &Interval::new(0.001, f32::INFINITY)) else {
+            return 0.0
+        };
+
+        let normal = self.u.cross(&self.v);
+
+        area_to_solid_angle(1.0 / normal.mag(), root * direction.mag(), normal.unit().dot(&direction.unit()))
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        self.corner + random() * self.u + random() * self.v - *origin
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// Flat disk facing along 'normal', which must be normalized.
+pub struct Disk {
+    pub center: Vec3D,
+    pub normal: Vec3D,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Disk {
+    /// Returns the ray parameter of the hit.
+    pub fn intersect(&self, ray: &Ray, t: &Interval) -> Option<f32> {
+        let denom = self.normal.dot(&ray.direction);
+
+        if denom.abs() < f32::EPSILON {
+            return None
+        }
+
+        let root = (self.center - ray.origin).dot(&self.normal) / denom;
+        if t.surrounds(root) || (ray.at(root) - self.center).mag2() > self.radius.powi(2) {
+            return None
+        }
+
+        Some(root)
+    }
+}
+
+impl Body for Disk {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some(root) = self.intersect(ray, &t) else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        hit_record.set_face_normal(ray, &self.normal);
+        hit_record.material = self.material.clone();
+
+        // The square around the disk maps to [0, 1] in both coordinates.
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let offset = (hit_record.point - self.center) / self.radius;
+        hit_record.u = 0.5 * (offset.dot(&tangent) + 1.0);
+        hit_record.v = 0.5 * (offset.dot(&bitangent) + 1.0);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // The disk spans 'radius * sin' of the angle between the normal and each axis.
+        let extent = |n: f32| self.radius * (1.0 - n.powi(2)).max(0.0).sqrt();
+        let extent = Vec3D::new(extent(self.normal.x), extent(self.normal.y), extent(self.normal.z));
+
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+
+    /// Uniform over the area of the disk, converted to solid angle.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let Some(root) = self.intersect(&Ray::new(origin, direction), &Interval::new(0.001, f32::INFINITY)) else {
+            return 0.0
+        };
+
+        let area = f32::consts::PI * self.radius.powi(2);
+
+        area_to_solid_angle(1.0 / area, root * direction.mag(), self.normal.dot(&direction.unit()))
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let radius = self.radius * random().sqrt();
+        let phi = 2.0 * f32::consts::PI * random();
+
+        self.center + radius * (phi.cos() * tangent + phi.sin() * bitangent) - *origin
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// Axis-aligned box made of six quads facing outwards.
+pub struct Cuboid {
+    sides: BodyList,
+    material: Arc<dyn Material>,
+}
+
+impl Cuboid {
+    /// Box spanned by two opposite corners, in any order.
+    pub fn new(a: &Vec3D, b: &Vec3D, material: Arc<dyn Material>) -> Self {
+        let min = Vec3D::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = Vec3D::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+        let dx = Vec3D::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3D::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3D::new(0.0, 0.0, max.z - min.z);
+
+        let mut sides = BodyList::new();
+        for (corner, u, v) in [
+            (Vec3D::new(min.x, min.y, max.z), dx, dy),
+            (Vec3D::new(max.x, min.y, max.z), -dz, dy),
+            (Vec3D::new(max.x, min.y, min.z), -dx, dy),
+            (min, dz, dy),
+            (Vec3D::new(min.x, max.y, max.z), dx, -dz),
+            (min, dx, dz),
+        ] {
+            sides.push(Arc::new(Quad { corner, u, v, material: material.clone() }));
+        }
+
+        Self { sides, material }
+    }
+
+    pub fn sides(&self) -> &BodyList {
+        &self.sides
+    }
+}
+
+impl Body for Cuboid {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        self.sides.bounding_box().hit(ray, t) && self.sides.hit(ray, t, hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sides.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        self.sides.pdf_value(origin, direction)
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        self.sides.random_direction(origin)
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// Capped cylinder standing on the vertical axis through 'center', which is
+/// halfway up its 'height'.
+pub struct Cylinder {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub height: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Body for Cylinder {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some((root, outward_normal, u, v)) = intersect_frustum(ray, &self.center, self.radius, self.radius, self.height, &t) else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        hit_record.set_face_normal(ray, &outward_normal);
+        hit_record.material = self.material.clone();
+        hit_record.u = u;
+        hit_record.v = v;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3D::new(self.radius, self.height / 2.0, self.radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// Capped cone on the vertical axis through 'center', narrowing from 'radius'
+/// at the bottom to 'top_radius' at the top, 'height' above. A 'top_radius'
+/// of zero gives a pointed cone, anything more truncates it.
+pub struct Cone {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub top_radius: f32,
+    pub height: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Body for Cone {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some((root, outward_normal, u, v)) = intersect_frustum(ray, &self.center, self.radius, self.top_radius, self.height, &t) else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        hit_record.set_face_normal(ray, &outward_normal);
+        hit_record.material = self.material.clone();
+        hit_record.u = u;
+        hit_record.v = v;
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = self.radius.max(self.top_radius);
+        let extent = Vec3D::new(radius, self.height / 2.0, radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// Which part of a cylinder or cone a ray hit.
+#[derive(Clone, Copy)]
+enum Surface {
+    Side,
+    Bottom,
+    Top,
+}
+
+/// Hits the side and caps of a solid around the vertical axis through
+/// 'center', whose radius changes linearly from 'bottom_radius' at the bottom
+/// to 'top_radius' at the top. Returns the ray parameter, the outward normal
+/// and the surface coordinates.
+fn intersect_frustum(
+    ray: &Ray,
+    center: &Vec3D,
+    bottom_radius: f32,
+    top_radius: f32,
+    height: f32,
+    t: &Interval,
+) -> Option<(f32, Vec3D, f32, f32)> {
+    let origin = ray.origin - *center;
+    let direction = ray.direction;
+    let half_height = height / 2.0;
+
+    // 'x² + z² = r(y)²' with 'r' growing by 'slope' per unit of height.
+    let slope = (top_radius - bottom_radius) / height;
+    let radius_at_origin = bottom_radius + slope * (origin.y + half_height);
+
+    let a = direction.x.powi(2) + direction.z.powi(2) - (slope * direction.y).powi(2);
+    let half_b = origin.x * direction.x + origin.z * direction.z - slope * direction.y * radius_at_origin;
+    let c = origin.x.powi(2) + origin.z.powi(2) - radius_at_origin.powi(2);
+
+    let mut closest: Option<(f32, Surface)> = None;
+    let mut consider = |root: f32, surface: Surface| {
+        if !t.surrounds(root) && closest.is_none_or(|(closest, _)| root < closest) {
+            closest = Some((root, surface));
+        }
+    };
+
+    for root in quadratic_roots(a, half_b, c).into_iter().flatten() {
+        if (origin.y + root * direction.y).abs() <= half_height {
+            consider(root, Surface::Side);
+        }
+    }
+
+    if direction.y != 0.0 {
+        for (y, radius, surface) in [(-half_height, bottom_radius, Surface::Bottom), (half_height, top_radius, Surface::Top)] {
+            let root = (y - origin.y) / direction.y;
+            let point = origin + root * direction;
+
+            if radius > 0.0 && point.x.powi(2) + point.z.powi(2) <= radius.powi(2) {
+                consider(root, surface);
+            }
+        }
+    }
+
+    let (root, surface) = closest?;
+
+    let point = origin + root * direction;
+    let (outward_normal, u, v) = match surface {
+        Surface::Side => {
+            // Gradient of 'x² + z² - r(y)²'; pointed cones have none at the apex.
+            let radius = bottom_radius + slope * (point.y + half_height);
+            let normal = Vec3D::new(point.x, -radius * slope, point.z);
+            let normal = if normal.is_near_zero() { Vec3D::y_unit() } else { normal.unit() };
+            let u = ((-point.z).atan2(point.x) + f32::consts::PI) / (2.0 * f32::consts::PI);
+
+            (normal, u, (point.y + half_height) / height)
+        },
+        Surface::Bottom => (-Vec3D::y_unit(), 0.5 * (point.x / bottom_radius + 1.0), 0.5 * (point.z / bottom_radius + 1.0)),
+        Surface::Top => (Vec3D::y_unit(), 0.5 * (point.x / top_radius + 1.0), 0.5 * (point.z / top_radius + 1.0)),
+    };
+
+    Some((root, outward_normal, u, v))
+}
+
+/// Ring around the vertical axis through 'center', whose tube of
+/// 'tube_radius' follows a circle of 'radius' in the horizontal plane.
+pub struct Torus {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub tube_radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Body for Torus {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        // Solved for the distance along the ray in units of the bounding radius,
+        // starting just outside the bounding sphere, which keeps the quartic
+        // well conditioned however far away the ray starts.
+        let bound = self.radius + self.tube_radius;
+        let length = ray.direction.mag();
+        let direction = ray.direction / length;
+        let origin = (ray.origin - self.center) / bound;
+
+        let closest_approach = -origin.dot(&direction);
+        if (origin + closest_approach * direction).mag2() > 1.0 {
+            return false
+        }
+
+        let shift = (closest_approach - 1.0).max(0.0);
+        let origin = origin + shift * direction;
+
+        let (radius, tube_radius) = ((self.radius / bound) as f64, (self.tube_radius / bound) as f64);
+        let (o, d) = ([origin.x, origin.y, origin.z].map(f64::from), [direction.x, direction.y, direction.z].map(f64::from));
+
+        // '(|p|² - R² - r²)² = 4R²(r² - y²)' along the ray.
+        let f = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let e = o[0].powi(2) + o[1].powi(2) + o[2].powi(2) - radius.powi(2) - tube_radius.powi(2);
+        let four_r2 = 4.0 * radius.powi(2);
+
+        let (roots, count) = quartic_roots(
+            4.0 * f,
+            2.0 * e + 4.0 * f.powi(2) + four_r2 * d[1].powi(2),
+            4.0 * f * e + 2.0 * four_r2 * o[1] * d[1],
+            e.powi(2) - four_r2 * (tube_radius.powi(2) - o[1].powi(2)),
+        );
+
+        let Some(root) = roots[..count].iter()
+            .map(|&distance| (distance as f32 + shift) * bound / length)
+            .filter(|&root| !t.surrounds(root))
+            .min_by(|a, b| a.total_cmp(b))
+        else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+
+        let local = hit_record.point - self.center;
+        let ring = Vec3D::new(local.x, 0.0, local.z);
+        let ring_distance = ring.mag();
+        let outward_normal = if ring_distance > 0.0 { local - ring * (self.radius / ring_distance) } else { local };
+        hit_record.set_face_normal(ray, &outward_normal.unit());
+        hit_record.material = self.material.clone();
+
+        // 'u' around the vertical axis, 'v' around the tube starting on its inside.
+        hit_record.u = ((-local.z).atan2(local.x) + f32::consts::PI) / (2.0 * f32::consts::PI);
+        hit_record.v = (local.y.atan2(ring_distance - self.radius) + f32::consts::PI) / (2.0 * f32::consts::PI);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3D::new(self.radius + self.tube_radius, self.tube_radius, self.radius + self.tube_radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// Cylinder of 'height' on the vertical axis through 'center', closed by
+/// half spheres of the same 'radius' at both ends.
+pub struct Capsule {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub height: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Body for Capsule {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let origin = ray.origin - self.center;
+        let direction = ray.direction;
+        let half_height = self.height / 2.0;
+
+        let mut closest: Option<f32> = None;
+        let mut consider = |root: f32| {
+            if !t.surrounds(root) && closest.is_none_or(|closest| root < closest) {
+                closest = Some(root);
+            }
+        };
+
+        let a = direction.x.powi(2) + direction.z.powi(2);
+        let half_b = origin.x * direction.x + origin.z * direction.z;
+        let c = origin.x.powi(2) + origin.z.powi(2) - self.radius.powi(2);
+
+        for root in quadratic_roots(a, half_b, c).into_iter().flatten() {
+            if (origin.y + root * direction.y).abs() <= half_height {
+                consider(root);
+            }
+        }
+
+        // Each end only counts beyond the straight part.
+        for side in [-1.0, 1.0] {
+            let offset = origin - Vec3D::new(0.0, side * half_height, 0.0);
+            let half_b = direction.dot(&offset);
+            let c = offset.mag2() - self.radius.powi(2);
+
+            for root in quadratic_roots(direction.mag2(), half_b, c).into_iter().flatten() {
+                if side * (origin.y + root * direction.y) > half_height {
+                    consider(root);
+                }
+            }
+        }
+
+        let Some(root) = closest else {
+            return false
+        };
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+
+        // The normal points away from the closest point on the axis.
+        let local = hit_record.point - self.center;
+        let axis_point = Vec3D::new(0.0, local.y.clamp(-half_height, half_height), 0.0);
+        hit_record.set_face_normal(ray, &((local - axis_point) / self.radius));
+        hit_record.material = self.material.clone();
+
+        hit_record.u = ((-local.z).atan2(local.x) + f32::consts::PI) / (2.0 * f32::consts::PI);
+        hit_record.v = ((local.y + half_height + self.radius) / (self.height + 2.0 * self.radius)).clamp(0.0, 1.0);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3D::new(self.radius, self.height / 2.0 + self.radius, self.radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// Uniformly distributed over the triangle.
+pub fn random_point_in_triangle(a: &Vec3D, b: &Vec3D, c: &Vec3D) -> Vec3D {
+    let s = random().sqrt();
+    let t = random();
+
+    (1.0 - s) * *a + s * (1.0 - t) * *b + s * t * *c
+}
+
+/// Converts a density per unit area at a point 'distance' away, whose surface
+/// normal makes 'cosine' with the line of sight, into a density per unit solid angle.
+pub fn area_to_solid_angle(pdf_area: f32, distance: f32, cosine: f32) -> f32 {
+    let cosine = cosine.abs();
+    if cosine < f32::EPSILON {
+        return 0.0
+    }
+
+    pdf_area * distance.powi(2) / cosine
+}
+
+/// '1 - cos_theta_max' of the cone a sphere covers, given 'sin2_theta_max',
+/// its radius squared over its distance squared. Written without the
+/// cancellation that makes far away spheres cover no solid angle at all.
+fn one_minus_cos_theta_max(sin2_theta_max: f32) -> f32 {
+    sin2_theta_max / (1.0 + (1.0 - sin2_theta_max).sqrt())
+}
+
+/// Real roots of 'a x² + 2 half_b x + c', computed without the cancellation
+/// of the textbook formula so 'a' may be close to zero.
+fn quadratic_roots(a: f32, half_b: f32, c: f32) -> [Option<f32>; 2] {
+    let discriminant = half_b.powi(2) - a * c;
+    if discriminant < 0.0 {
+        return [None, None]
+    }
+
+    let q = -(half_b + discriminant.sqrt().copysign(half_b));
+
+    [(a != 0.0).then(|| q / a), (q != 0.0).then(|| c / q)]
+}
+
+const POLYNOMIAL_EPSILON: f64 = 1e-12;
+
+/// Real roots of 'x³ + a x² + b x + c', by Cardano's formula.
+fn cubic_roots(a: f64, b: f64, c: f64) -> ([f64; 3], usize) {
+    // Substituting 'x = y - a / 3' leaves 'y³ + 3p y + 2q'.
+    let p = (b - a.powi(2) / 3.0) / 3.0;
+    let q = (2.0 / 27.0 * a.powi(3) - a * b / 3.0 + c) / 2.0;
+    let discriminant = q.powi(2) + p.powi(3);
+
+    let mut roots = [0.0; 3];
+    let count = if discriminant.abs() < POLYNOMIAL_EPSILON {
+        if q.abs() < POLYNOMIAL_EPSILON {
+            1
+        } else {
+            let u = (-q).cbrt();
+            roots[..2].copy_from_slice(&[2.0 * u, -u]);
+            2
+        }
+    } else if discriminant < 0.0 {
+        let phi = (-q / (-p.powi(3)).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let scale = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::PI / 3.0;
+        roots = [scale * phi.cos(), -scale * (phi + third).cos(), -scale * (phi - third).cos()];
+        3
+    } else {
+        let sqrt_discriminant = discriminant.sqrt();
+        roots[0] = (sqrt_discriminant - q).cbrt() - (sqrt_discriminant + q).cbrt();
+        1
+    };
+
+    for root in &mut roots[..count] {
+        *root -= a / 3.0;
+    }
+
+    (roots, count)
+}
+
+/// Real roots of 'x⁴ + a x³ + b x² + c x + d', by Ferrari's method as
+/// arranged by Schwarze in Graphics Gems, polished with Newton's method.
+fn quartic_roots(a: f64, b: f64, c: f64, d: f64) -> ([f64; 4], usize) {
+    // Substituting 'x = y - a / 4' leaves 'y⁴ + p y² + q y + r'.
+    let p = -3.0 / 8.0 * a.powi(2) + b;
+    let q = a.powi(3) / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * a.powi(4) + a.powi(2) * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = [0.0; 4];
+    let mut count = 0;
+
+    if r.abs() < POLYNOMIAL_EPSILON {
+        let (cubic, cubic_count) = cubic_roots(0.0, p, q);
+        roots[..cubic_count].copy_from_slice(&cubic[..cubic_count]);
+        count = cubic_count + 1;
+    } else {
+        // Any root of the resolvent cubic splits the quartic into two quadratics.
+        let (cubic, _) = cubic_roots(-p / 2.0, -r, r * p / 2.0 - q.powi(2) / 8.0);
+        let z = cubic[0];
+
+        let square_root = |x: f64| if x.abs() < POLYNOMIAL_EPSILON { Some(0.0) } else if x > 0.0 { Some(x.sqrt()) } else { None };
+        let (Some(u), Some(v)) = (square_root(z.powi(2) - r), square_root(2.0 * z - p)) else {
+            return (roots, 0)
+        };
+
+        let v = if q < 0.0 { -v } else { v };
+        for (linear, constant) in [(v, z - u), (-v, z + u)] {
+            let half = linear / 2.0;
+            let discriminant = half.powi(2) - constant;
+
+            if discriminant.abs() < POLYNOMIAL_EPSILON {
+                roots[count] = -half;
+                count += 1;
+            } else if discriminant > 0.0 {
+                roots[count] = discriminant.sqrt() - half;
+                roots[count + 1] = -discriminant.sqrt() - half;
+                count += 2;
+            }
+        }
+    }
+
+    for root in &mut roots[..count] {
+        *root -= a / 4.0;
+
+        for _ in 0..2 {
+            let value = (((*root + a) * *root + b) * *root + c) * *root + d;
+            let derivative = ((4.0 * *root + 3.0 * a) * *root + 2.0 * b) * *root + c;
+            if derivative != 0.0 {
+                *root -= value / derivative;
+            }
+        }
+    }
+
+    (roots, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::materials::Lambertian;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3D::one()))
+    }
+
+    const ALL: Interval = Interval::new(0.001, f32::INFINITY);
+
+    fn hit(body: &dyn Body, origin: Vec3D, direction: Vec3D) -> Option<HitRecord> {
+        let mut hit_record = HitRecord::new();
+        body.hit(&Ray::new(&origin, &direction), ALL, &mut hit_record).then_some(hit_record)
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} is not {}", a, b);
+    }
+
+    fn assert_near_vector(a: Vec3D, b: Vec3D) {
+        assert!((a - b).mag() < 1e-4, "{:?} is not {:?}", a, b);
+    }
+
+    /// Right triangle in the plane z = 0, facing +z.
+    fn triangle() -> Triangle {
+        Triangle { a: Vec3D::zero(), b: Vec3D::x_unit(), c: Vec3D::y_unit(), material: material() }
+    }
+
+    #[test]
+    fn triangle_hits_report_barycentric_weights() {
+        let hit_record = hit(&triangle(), Vec3D::new(0.25, 0.5, 2.0), -Vec3D::z_unit()).unwrap();
+
+        assert_near(hit_record.t, 2.0);
+        assert_near_vector(hit_record.point, Vec3D::new(0.25, 0.5, 0.0));
+        assert_near_vector(hit_record.barycentric, Vec3D::new(0.25, 0.25, 0.5));
+        assert!(hit_record.front_face);
+        assert_near_vector(hit_record.normal, Vec3D::z_unit());
+    }
+
+    #[test]
+    fn triangle_back_faces_point_towards_the_ray() {
+        let hit_record = hit(&triangle(), Vec3D::new(0.25, 0.25, -1.0), Vec3D::z_unit()).unwrap();
+
+        assert!(!hit_record.front_face);
+        assert_near_vector(hit_record.normal, -Vec3D::z_unit());
+    }
+
+    #[test]
+    fn triangle_misses_outside_its_edges() {
+        let triangle = triangle();
+
+        assert!(hit(&triangle, Vec3D::new(0.6, 0.6, 1.0), -Vec3D::z_unit()).is_none());
+        assert!(hit(&triangle, Vec3D::new(-0.1, 0.5, 1.0), -Vec3D::z_unit()).is_none());
+        assert!(hit(&triangle, Vec3D::new(0.5, -0.1, 1.0), -Vec3D::z_unit()).is_none());
+    }
+
+    #[test]
+    fn triangle_misses_parallel_and_receding_rays() {
+        let triangle = triangle();
+
+        assert!(hit(&triangle, Vec3D::new(-1.0, 0.25, 0.0), Vec3D::x_unit()).is_none());
+        assert!(hit(&triangle, Vec3D::new(0.25, 0.25, 1.0), Vec3D::z_unit()).is_none());
+
+        let ray = Ray::new(&Vec3D::new(0.25, 0.25, 2.0), &-Vec3D::z_unit());
+        assert!(!triangle.hit(&ray, Interval::new(0.001, 1.5), &mut HitRecord::new()));
+    }
+
+    #[test]
+    fn quad_hits_report_positions_along_the_edges() {
+        let quad = Quad { corner: Vec3D::zero(), u: Vec3D::new(2.0, 0.0, 0.0), v: Vec3D::new(1.0, 1.0, 0.0), material: material() };
+        let hit_record = hit(&quad, Vec3D::new(2.0, 0.5, 3.0), -Vec3D::z_unit()).unwrap();
+
+        assert_near(hit_record.t, 3.0);
+        assert_near(hit_record.u, 0.75);
+        assert_near(hit_record.v, 0.5);
+        assert!(hit_record.front_face);
+    }
+
+    #[test]
+    fn quad_misses_outside_the_parallelogram() {
+        let quad = Quad { corner: Vec3D::zero(), u: Vec3D::new(2.0, 0.0, 0.0), v: Vec3D::new(1.0, 1.0, 0.0), material: material() };
+
+        // Inside the bounding box, but left of the slanted edge.
+        assert!(hit(&quad, Vec3D::new(0.2, 0.8, 1.0), -Vec3D::z_unit()).is_none());
+        assert!(hit(&quad, Vec3D::new(2.8, 0.5, 1.0), -Vec3D::z_unit()).is_none());
+        assert!(hit(&quad, Vec3D::new(-1.0, 0.5, 0.0), Vec3D::x_unit()).is_none());
+
+        let back = hit(&quad, Vec3D::new(1.0, 0.5, -1.0), Vec3D::z_unit()).unwrap();
+        assert!(!back.front_face);
+    }
+
+    #[test]
+    fn disk_hits_within_its_radius() {
+        let disk = Disk { center: Vec3D::new(0.0, 1.0, 0.0), normal: Vec3D::y_unit(), radius: 2.0, material: material() };
+
+        let hit_record = hit(&disk, Vec3D::new(1.5, 3.0, 0.0), -Vec3D::y_unit()).unwrap();
+        assert_near(hit_record.t, 2.0);
+        assert!(hit_record.front_face);
+
+        assert!(hit(&disk, Vec3D::new(1.5, 3.0, 1.5), -Vec3D::y_unit()).is_none());
+    }
+
+    #[test]
+    fn disk_bounds_are_flat_along_the_normal() {
+        let disk = Disk { center: Vec3D::zero(), normal: Vec3D::z_unit(), radius: 2.0, material: material() };
+        let bounding_box = disk.bounding_box();
+
+        assert_near(bounding_box.x.max, 2.0);
+        assert_near(bounding_box.y.min, -2.0);
+        // Padded, so it can still be hit edge on.
+        assert!(bounding_box.z.max - bounding_box.z.min < 1e-3);
+    }
+
+    #[test]
+    fn cuboid_sides_face_outwards() {
+        let cuboid = Cuboid::new(&Vec3D::new(1.0, 1.0, 1.0), &Vec3D::new(-1.0, -1.0, -1.0), material());
+
+        for axis in [Vec3D::x_unit(), Vec3D::y_unit(), Vec3D::z_unit()] {
+            for direction in [axis, -axis] {
+                let hit_record = hit(&cuboid, -3.0 * direction, direction).unwrap();
+
+                assert_near(hit_record.t, 2.0);
+                assert!(hit_record.front_face, "{:?}", direction);
+                assert_near_vector(hit_record.normal, -direction);
+            }
+        }
+    }
+
+    #[test]
+    fn cuboid_is_left_from_the_inside() {
+        let cuboid = Cuboid::new(&Vec3D::zero(), &Vec3D::new(2.0, 1.0, 1.0), material());
+        let hit_record = hit(&cuboid, Vec3D::new(0.5, 0.5, 0.5), Vec3D::x_unit()).unwrap();
+
+        assert_near(hit_record.t, 1.5);
+        assert!(!hit_record.front_face);
+    }
+
+    /// Ring of radius 2 around the y axis with a tube of radius 0.5.
+    fn torus() -> Torus {
+        Torus { center: Vec3D::zero(), radius: 2.0, tube_radius: 0.5, material: material() }
+    }
+
+    #[test]
+    fn torus_hits_the_outside_of_the_tube() {
+        let hit_record = hit(&torus(), Vec3D::new(-10.0, 0.0, 0.0), Vec3D::x_unit()).unwrap();
+        assert_near(hit_record.t, 7.5);
+        assert_near_vector(hit_record.normal, -Vec3D::x_unit());
+
+        let hit_record = hit(&torus(), Vec3D::new(0.0, 10.0, 2.0), -Vec3D::y_unit()).unwrap();
+        assert_near(hit_record.t, 9.5);
+        assert_near_vector(hit_record.normal, Vec3D::y_unit());
+    }
+
+    #[test]
+    fn torus_misses_through_its_hole_and_above_its_tube() {
+        assert!(hit(&torus(), Vec3D::new(0.0, 10.0, 0.0), -Vec3D::y_unit()).is_none());
+        assert!(hit(&torus(), Vec3D::new(1.0, 10.0, 0.5), -Vec3D::y_unit()).is_none());
+        assert!(hit(&torus(), Vec3D::new(-10.0, 0.6, 0.0), Vec3D::x_unit()).is_none());
+    }
+
+    #[test]
+    fn torus_is_left_from_inside_the_tube() {
+        let hit_record = hit(&torus(), Vec3D::new(2.0, 0.0, 0.0), Vec3D::x_unit()).unwrap();
+
+        assert_near(hit_record.t, 0.5);
+        assert!(!hit_record.front_face);
+    }
+
+    #[test]
+    fn torus_stays_accurate_from_far_away() {
+        let distance = 1.0e4;
+        let hit_record = hit(&torus(), Vec3D::new(0.0, 0.0, -distance), Vec3D::z_unit()).unwrap();
+
+        // The near side of the tube crossing the z axis, not the far one.
+        assert!((hit_record.point.z + 2.5).abs() < 1e-2, "{:?}", hit_record.point);
+    }
+
+    #[test]
+    fn cylinder_hits_its_side_and_caps() {
+        let cylinder = Cylinder { center: Vec3D::zero(), radius: 1.0, height: 2.0, material: material() };
+
+        let side = hit(&cylinder, Vec3D::new(-5.0, 0.5, 0.0), Vec3D::x_unit()).unwrap();
+        assert_near(side.t, 4.0);
+        assert_near_vector(side.normal, -Vec3D::x_unit());
+
+        let top = hit(&cylinder, Vec3D::new(0.5, 5.0, 0.0), -Vec3D::y_unit()).unwrap();
+        assert_near(top.t, 4.0);
+        assert_near_vector(top.normal, Vec3D::y_unit());
+
+        assert!(hit(&cylinder, Vec3D::new(-5.0, 1.5, 0.0), Vec3D::x_unit()).is_none());
+    }
+
+    #[test]
+    fn cone_narrows_towards_its_apex() {
+        let cone = Cone { center: Vec3D::zero(), radius: 1.0, top_radius: 0.0, height: 2.0, material: material() };
+
+        // Halfway up, the radius is halved.
+        let hit_record = hit(&cone, Vec3D::new(-5.0, 0.0, 0.0), Vec3D::x_unit()).unwrap();
+        assert_near(hit_record.t, 4.5);
+        assert!(hit_record.normal.y > 0.0 && hit_record.normal.x < 0.0);
+
+        assert!(hit(&cone, Vec3D::new(-5.0, 0.9, 0.2), Vec3D::x_unit()).is_none());
+    }
+
+    #[test]
+    fn capsule_is_rounded_at_its_ends() {
+        let capsule = Capsule { center: Vec3D::zero(), radius: 1.0, height: 2.0, material: material() };
+
+        let top = hit(&capsule, Vec3D::new(0.0, 5.0, 0.0), -Vec3D::y_unit()).unwrap();
+        assert_near(top.t, 3.0);
+        assert_near_vector(top.normal, Vec3D::y_unit());
+
+        let side = hit(&capsule, Vec3D::new(-5.0, 0.5, 0.0), Vec3D::x_unit()).unwrap();
+        assert_near(side.t, 4.0);
+
+        // Outside the end sphere, though within the cylinder it rounds off.
+        assert!(hit(&capsule, Vec3D::new(-5.0, 1.9, 0.9), Vec3D::x_unit()).is_none());
+    }
+}
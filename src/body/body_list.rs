@@ -1,28 +1,56 @@
-use std::{rc::Rc, cell::RefCell};
+use std::sync::Arc;
 
-use crate::interval::Interval;
+use crate::{vector::Vec3D, interval::Interval, aabb::{self, Aabb}, random};
 
 use super::{Body, HitRecord};
 
 pub struct BodyList {
-    bodies: Vec<Rc<RefCell<dyn Body>>>
+    bodies: Vec<Arc<dyn Body>>,
+    bounding_box: Aabb,
 }
 
 impl BodyList {
     pub fn new() -> Self {
-        Self { bodies: vec![] }
+        Self { bodies: vec![], bounding_box: aabb::EMPTY }
     }
 
-    pub fn push(&mut self, body: Rc<RefCell<dyn Body>>) {
+    pub fn push(&mut self, body: Arc<dyn Body>) {
+        self.bounding_box = Aabb::enclosing(&self.bounding_box, &body.bounding_box());
         self.bodies.push(body)
     }
 
-    pub fn pop(&mut self) -> Option<Rc<RefCell<dyn Body>>> {
-        self.bodies.pop()
+    pub fn pop(&mut self) -> Option<Arc<dyn Body>> {
+        let body = self.bodies.pop();
+        self.update_bounding_box();
+        body
     }
 
     pub fn clear(&mut self) {
-        self.bodies.clear()
+        self.bodies.clear();
+        self.bounding_box = aabb::EMPTY;
+    }
+
+    pub fn bodies(&self) -> &[Arc<dyn Body>] {
+        &self.bodies
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    fn update_bounding_box(&mut self) {
+        self.bounding_box = self.bodies.iter()
+            .fold(aabb::EMPTY, |bounding_box, body| Aabb::enclosing(&bounding_box, &body.bounding_box()));
+    }
+}
+
+impl Default for BodyList {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -33,7 +61,7 @@ impl Body for BodyList {
         let mut closest = t.max;
 
         for body in &self.bodies {
-            if body.borrow().hit(ray, Interval::new(t.min, closest), &mut temp_hit_record) {
+            if body.hit(ray, Interval::new(t.min, closest), &mut temp_hit_record) {
                 hit = true;
                 closest = temp_hit_record.t;
                 *hit_record = temp_hit_record.clone();
@@ -42,4 +70,48 @@ impl Body for BodyList {
 
         hit
     }
-}
\ No newline at end of file
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn transmittance(&self, ray: &crate::ray::Ray, t: Interval) -> f32 {
+        let mut transmittance = 1.0;
+
+        for body in &self.bodies {
+            transmittance *= body.transmittance(ray, t);
+            if transmittance <= 0.0 {
+                break
+            }
+        }
+
+        transmittance
+    }
+
+    /// Every body is equally likely to be sampled.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        if self.bodies.is_empty() {
+            return 0.0
+        }
+
+        let sum: f32 = self.bodies.iter().map(|body| body.pdf_value(origin, direction)).sum();
+
+        sum / self.bodies.len() as f32
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        if self.bodies.is_empty() {
+            return Vec3D::x_unit()
+        }
+
+        let index = ((random() * self.bodies.len() as f32) as usize).min(self.bodies.len() - 1);
+
+        self.bodies[index].random_direction(origin)
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        for body in &self.bodies {
+            body.clone().collect_lights(lights);
+        }
+    }
+}
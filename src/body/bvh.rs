@@ -0,0 +1,252 @@
+use std::{sync::Arc, cmp::Ordering};
+
+use crate::{interval::Interval, aabb::{self, Aabb}, ray::Ray};
+
+use super::{Body, HitRecord, body_list::BodyList};
+
+/// How a set of bodies is divided between the two children of a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// Split at the median centroid along the longest axis. Fast to build.
+    Median,
+    /// Pick the axis and split position minimizing the surface area heuristic.
+    /// Slower to build, faster to trace.
+    SurfaceAreaHeuristic,
+}
+
+/// Bounding volume hierarchy node. Any body can be a child, so a leaf is
+/// simply the body itself.
+pub struct BvhNode {
+    left: Arc<dyn Body>,
+    right: Arc<dyn Body>,
+    bounding_box: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(list: &BodyList, split_method: SplitMethod) -> Self {
+        // Unbounded bodies (e.g. planes) cannot be partitioned spatially,
+        // so they are kept in a flat list next to the tree.
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = list.bodies().iter()
+            .cloned()
+            .partition(|body| body.bounding_box().is_bounded());
+
+        let mut unbounded_list = BodyList::new();
+        for body in unbounded {
+            unbounded_list.push(body);
+        }
+
+        match bounded.len() {
+            0 => Self::from_children(Arc::new(unbounded_list), Arc::new(BodyList::new())),
+            1 => Self::from_children(bounded[0].clone(), Arc::new(unbounded_list)),
+            _ if unbounded_list.is_empty() => Self::build(bounded, split_method),
+            _ => Self::from_children(
+                Arc::new(Self::build(bounded, split_method)),
+                Arc::new(unbounded_list)
+            ),
+        }
+    }
+
+    fn from_children(left: Arc<dyn Body>, right: Arc<dyn Body>) -> Self {
+        let bounding_box = Aabb::enclosing(&left.bounding_box(), &right.bounding_box());
+
+        Self { left, right, bounding_box }
+    }
+
+    /// 'bodies' must contain at least two bounded bodies.
+    fn build(mut bodies: Vec<Arc<dyn Body>>, split_method: SplitMethod) -> Self {
+        if bodies.len() == 2 {
+            return Self::from_children(bodies[0].clone(), bodies[1].clone())
+        }
+
+        let (axis, mid) = match split_method {
+            SplitMethod::Median => {
+                let centroid_box = bodies.iter().fold(aabb::EMPTY, |bounding_box, body| {
+                    let centroid = body.bounding_box().centroid();
+                    Aabb::enclosing(&bounding_box, &Aabb::from_points(&centroid, &centroid))
+                });
+
+                (centroid_box.longest_axis(), bodies.len() / 2)
+            },
+            SplitMethod::SurfaceAreaHeuristic => Self::surface_area_split(&mut bodies),
+        };
+
+        bodies.sort_by(|a, b| Self::compare_centroids(a, b, axis));
+        let right = bodies.split_off(mid);
+
+        Self::from_children(Self::subtree(bodies, split_method), Self::subtree(right, split_method))
+    }
+
+    fn subtree(mut bodies: Vec<Arc<dyn Body>>, split_method: SplitMethod) -> Arc<dyn Body> {
+        if bodies.len() == 1 {
+            bodies.pop().unwrap()
+        } else {
+            Arc::new(Self::build(bodies, split_method))
+        }
+    }
+
+    /// Returns the axis and index minimizing 'left_count * left_area + right_count * right_area'.
+    fn surface_area_split(bodies: &mut [Arc<dyn Body>]) -> (usize, usize) {
+        let n = bodies.len();
+        let mut best = (0, n / 2);
+        let mut best_cost = f32::INFINITY;
+
+        let mut right_areas = vec![0.0; n];
+
+        for axis in 0..3 {
+            bodies.sort_by(|a, b| Self::compare_centroids(a, b, axis));
+
+            let mut right_box = aabb::EMPTY;
+            for i in (1..n).rev() {
+                right_box = Aabb::enclosing(&right_box, &bodies[i].bounding_box());
+                right_areas[i] = right_box.surface_area();
+            }
+
+            let mut left_box = aabb::EMPTY;
+            for i in 1..n {
+                left_box = Aabb::enclosing(&left_box, &bodies[i - 1].bounding_box());
+                let cost = i as f32 * left_box.surface_area() + (n - i) as f32 * right_areas[i];
+
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = (axis, i);
+                }
+            }
+        }
+
+        best
+    }
+
+    fn compare_centroids(a: &Arc<dyn Body>, b: &Arc<dyn Body>, axis: usize) -> Ordering {
+        let a = a.bounding_box();
+        let b = b.bounding_box();
+
+        (a.axis(axis).min + a.axis(axis).max).total_cmp(&(b.axis(axis).min + b.axis(axis).max))
+    }
+}
+
+impl Body for BvhNode {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        if !self.bounding_box.hit(ray, t) {
+            return false
+        }
+
+        let hit_left = self.left.hit(ray, t, hit_record);
+        let hit_right = self.right.hit(
+            ray,
+            Interval::new(t.min, if hit_left { hit_record.t } else { t.max }),
+            hit_record
+        );
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        if !self.bounding_box.hit(ray, t) {
+            return 1.0
+        }
+
+        let left = self.left.transmittance(ray, t);
+        if left <= 0.0 {
+            return 0.0
+        }
+
+        left * self.right.transmittance(ray, t)
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        self.left.clone().collect_lights(lights);
+        self.right.clone().collect_lights(lights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vector::Vec3D, material::{Material, materials::Lambertian}, body::bodies::{Sphere, Plane, Triangle}};
+
+    /// Deterministic numbers in [-1, 1), so failures can be reproduced.
+    struct Sequence(u64);
+
+    impl Sequence {
+        fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        }
+
+        fn vector(&mut self, scale: f32) -> Vec3D {
+            Vec3D::new(self.next(), self.next(), self.next()) * scale
+        }
+    }
+
+    fn scene(sequence: &mut Sequence) -> BodyList {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3D::one()));
+        let mut list = BodyList::new();
+
+        for _ in 0..60 {
+            list.push(Arc::new(Sphere { center: sequence.vector(10.0), radius: 0.2 + sequence.next().abs(), material: material.clone() }));
+        }
+
+        for _ in 0..20 {
+            let a = sequence.vector(10.0);
+            list.push(Arc::new(Triangle { a, b: a + sequence.vector(2.0), c: a + sequence.vector(2.0), material: material.clone() }));
+        }
+
+        list.push(Arc::new(Plane { center: Vec3D::new(0.0, -12.0, 0.0), normal: Vec3D::new(0.0, 1.0, 0.0), material }));
+
+        list
+    }
+
+    fn assert_matches_list(split_method: SplitMethod) {
+        let mut sequence = Sequence(7);
+        let list = scene(&mut sequence);
+        let bvh = BvhNode::new(&list, split_method);
+
+        let mut hits = 0;
+
+        for _ in 0..2000 {
+            let ray = Ray::new(&sequence.vector(15.0), &sequence.vector(1.0));
+            let t = Interval::new(0.001, f32::INFINITY);
+
+            let (mut expected, mut actual) = (HitRecord::new(), HitRecord::new());
+            let hit = list.hit(&ray, t, &mut expected);
+
+            assert_eq!(bvh.hit(&ray, t, &mut actual), hit);
+            if hit {
+                assert_eq!(actual.t, expected.t);
+                assert_eq!(actual.point, expected.point);
+                hits += 1;
+            }
+        }
+
+        // Otherwise the comparison says little.
+        assert!(hits > 500, "{}", hits);
+    }
+
+    #[test]
+    fn median_split_hits_like_a_list() {
+        assert_matches_list(SplitMethod::Median);
+    }
+
+    #[test]
+    fn surface_area_split_hits_like_a_list() {
+        assert_matches_list(SplitMethod::SurfaceAreaHeuristic);
+    }
+
+    #[test]
+    fn holds_only_unbounded_bodies() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3D::one()));
+        let mut list = BodyList::new();
+        list.push(Arc::new(Plane { center: Vec3D::zero(), normal: Vec3D::new(0.0, 1.0, 0.0), material }));
+
+        let bvh = BvhNode::new(&list, SplitMethod::Median);
+        let ray = Ray::new(&Vec3D::new(0.0, 1.0, 0.0), &Vec3D::new(0.0, -1.0, 0.0));
+
+        let mut hit_record = HitRecord::new();
+        assert!(bvh.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut hit_record));
+        assert_eq!(hit_record.t, 1.0);
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use crate::{ray::Ray, interval::Interval, aabb::Aabb};
+
+use super::{Body, HitRecord, Span};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Inside either body.
+    Union,
+    /// Inside both bodies.
+    Intersection,
+    /// Inside the left body but not the right one.
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Self::Union => in_left || in_right,
+            Self::Intersection => in_left && in_right,
+            Self::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Constructive solid geometry: the union, intersection or difference of
+/// two closed bodies. Each part of the surface keeps the material of the
+/// body it comes from, so a hole cut by 'Difference' has the material of the
+/// right body.
+pub struct Csg {
+    pub operation: CsgOperation,
+    left: Arc<dyn Body>,
+    right: Arc<dyn Body>,
+    bounding_box: Aabb,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Arc<dyn Body>, right: Arc<dyn Body>) -> Self {
+        let (a, b) = (left.bounding_box(), right.bounding_box());
+
+        let bounding_box = match operation {
+            CsgOperation::Union => Aabb::enclosing(&a, &b),
+            CsgOperation::Intersection => Aabb::new(
+                Interval::new(a.x.min.max(b.x.min), a.x.max.min(b.x.max)),
+                Interval::new(a.y.min.max(b.y.min), a.y.max.min(b.y.max)),
+                Interval::new(a.z.min.max(b.z.min), a.z.max.min(b.z.max)),
+            ),
+            CsgOperation::Difference => a,
+        };
+
+        Self { operation, left, right, bounding_box }
+    }
+
+    pub fn left(&self) -> &Arc<dyn Body> {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Arc<dyn Body> {
+        &self.right
+    }
+}
+
+impl Body for Csg {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        if !self.bounding_box.hit(ray, t) {
+            return false
+        }
+
+        // Only the first span matters, and only its finite ends lie within 't'.
+        let Some(span) = self.spans(ray, t).into_iter().next() else {
+            return false
+        };
+
+        *hit_record = if span.entry.t.is_finite() {
+            span.entry
+        } else if span.exit.t.is_finite() {
+            span.exit
+        } else {
+            return false
+        };
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// Walks the boundaries of both bodies in order, keeping those where
+    /// being inside the combination changes.
+    fn spans(&self, ray: &Ray, t: Interval) -> Vec<Span> {
+        // Whether the ray is inside either body at 't.max' depends on what lies beyond it.
+        let unbounded = Interval::new(t.min, f32::INFINITY);
+
+        let left = self.left.spans(ray, unbounded);
+        if left.is_empty() && self.operation != CsgOperation::Union {
+            return left
+        }
+
+        let right = self.right.spans(ray, unbounded);
+
+        // Each boundary with the side it belongs to and whether the ray enters that side there.
+        let mut boundaries: Vec<(&HitRecord, bool, bool)> = left.iter()
+            .map(|span| (span, true))
+            .chain(right.iter().map(|span| (span, false)))
+            .flat_map(|(span, is_left)| [(&span.entry, is_left, true), (&span.exit, is_left, false)])
+            .collect();
+        boundaries.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut entry: Option<HitRecord> = None;
+        let mut spans = vec![];
+
+        for (hit_record, is_left, entering) in boundaries {
+            let was_inside = self.operation.contains(in_left, in_right);
+            if is_left { in_left = entering } else { in_right = entering }
+            let inside = self.operation.contains(in_left, in_right);
+
+            if inside == was_inside {
+                continue
+            }
+
+            // The normal already faces the ray; only which side is outside can change,
+            // like on the surface a difference cuts out of the left body.
+            let mut hit_record = hit_record.clone();
+            hit_record.front_face = inside;
+
+            if inside {
+                entry = Some(hit_record);
+            } else if let Some(entry) = entry.take() {
+                // Boundaries of both bodies at the same place, like both starting
+                // before 't', can close a span as soon as it opens.
+                if hit_record.t > entry.t {
+                    spans.push(Span { entry, exit: hit_record });
+                }
+            }
+        }
+
+        Span::clip(spans, t.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vector::Vec3D, material::{Material, materials::Lambertian}, body::bodies::Sphere};
+
+    fn sphere(x: f32, material: &Arc<dyn Material>) -> Arc<dyn Body> {
+        Arc::new(Sphere { center: Vec3D::new(x, 0.0, 0.0), radius: 1.0, material: material.clone() })
+    }
+
+    fn materials() -> (Arc<dyn Material>, Arc<dyn Material>) {
+        (Arc::new(Lambertian::new(Vec3D::one())), Arc::new(Lambertian::new(Vec3D::zero())))
+    }
+
+    /// Spheres of radius 1 around x = 0 on the left and x = 1 on the right.
+    fn overlapping(operation: CsgOperation) -> Csg {
+        let (left, right) = materials();
+        Csg::new(operation, sphere(0.0, &left), sphere(1.0, &right))
+    }
+
+    /// Along the x axis from x = -10, so the ray parameter is 10 more than x.
+    fn along_x() -> Ray {
+        Ray::new(&Vec3D::new(-10.0, 0.0, 0.0), &Vec3D::x_unit())
+    }
+
+    fn ends(spans: &[Span]) -> Vec<(f32, f32)> {
+        spans.iter().map(|span| (span.entry.t, span.exit.t)).collect()
+    }
+
+    fn assert_ends(spans: &[Span], expected: &[(f32, f32)]) {
+        let actual = ends(spans);
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+
+        for (&(entry, exit), &(expected_entry, expected_exit)) in actual.iter().zip(expected) {
+            assert!((entry - expected_entry).abs() < 1e-4 && (exit - expected_exit).abs() < 1e-4, "{:?}", actual);
+        }
+    }
+
+    const ALL: Interval = Interval::new(0.001, f32::INFINITY);
+
+    #[test]
+    fn combines_spans() {
+        assert_ends(&overlapping(CsgOperation::Union).spans(&along_x(), ALL), &[(9.0, 12.0)]);
+        assert_ends(&overlapping(CsgOperation::Intersection).spans(&along_x(), ALL), &[(10.0, 11.0)]);
+        assert_ends(&overlapping(CsgOperation::Difference).spans(&along_x(), ALL), &[(9.0, 10.0)]);
+    }
+
+    #[test]
+    fn splits_spans_around_holes() {
+        let (left, right) = materials();
+        let wide: Arc<dyn Body> = Arc::new(Sphere { center: Vec3D::zero(), radius: 3.0, material: left });
+        let csg = Csg::new(CsgOperation::Difference, wide, sphere(0.0, &right));
+
+        assert_ends(&csg.spans(&along_x(), ALL), &[(7.0, 9.0), (11.0, 13.0)]);
+    }
+
+    #[test]
+    fn disjoint_bodies_only_have_a_union() {
+        let (left, right) = materials();
+        let (a, b) = (sphere(0.0, &left), sphere(5.0, &right));
+
+        assert_ends(&Csg::new(CsgOperation::Union, a.clone(), b.clone()).spans(&along_x(), ALL), &[(9.0, 11.0), (14.0, 16.0)]);
+        assert!(Csg::new(CsgOperation::Intersection, a.clone(), b.clone()).spans(&along_x(), ALL).is_empty());
+        assert!(!Csg::new(CsgOperation::Intersection, a, b).hit(&along_x(), ALL, &mut HitRecord::new()));
+    }
+
+    #[test]
+    fn holes_keep_the_material_of_the_cutting_body() {
+        let (left, right) = materials();
+        let csg = Csg::new(CsgOperation::Difference, sphere(0.0, &left), sphere(-1.0, &right));
+
+        let mut hit_record = HitRecord::new();
+        assert!(csg.hit(&along_x(), ALL, &mut hit_record));
+
+        // The right sphere covers the near side of the left one, up to x = 0.
+        assert!((hit_record.t - 10.0).abs() < 1e-4);
+        assert!(Arc::ptr_eq(&hit_record.material, &right));
+        assert!(hit_record.front_face);
+        assert!(hit_record.normal.dot(&Vec3D::x_unit()) < 0.0);
+    }
+
+    #[test]
+    fn rays_starting_inside_leave_through_the_far_side() {
+        let csg = overlapping(CsgOperation::Union);
+        let ray = Ray::new(&Vec3D::new(0.5, 0.0, 0.0), &Vec3D::x_unit());
+
+        let spans = csg.spans(&ray, ALL);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].entry.t, f32::NEG_INFINITY);
+
+        let mut hit_record = HitRecord::new();
+        assert!(csg.hit(&ray, ALL, &mut hit_record));
+        assert!((hit_record.t - 1.5).abs() < 1e-4);
+        assert!(!hit_record.front_face);
+    }
+
+    #[test]
+    fn nests() {
+        let (_, right) = materials();
+        let inner = Arc::new(overlapping(CsgOperation::Union));
+        let csg = Csg::new(CsgOperation::Intersection, inner, sphere(2.0, &right));
+
+        assert_ends(&csg.spans(&along_x(), ALL), &[(11.0, 12.0)]);
+    }
+
+    #[test]
+    fn clips_spans_to_the_interval() {
+        let csg = overlapping(CsgOperation::Union);
+        let spans = csg.spans(&along_x(), Interval::new(0.001, 10.0));
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].exit.t, f32::INFINITY);
+        assert!(csg.spans(&along_x(), Interval::new(0.001, 5.0)).is_empty());
+    }
+}
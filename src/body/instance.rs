@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::{self, Aabb}, transform::Transform};
+
+use super::{Body, HitRecord, Span, body_list::BodyList};
+
+/// Shared body placed with a transform, so the same geometry can appear many
+/// times while it is stored once.
+pub struct Instance {
+    body: Arc<dyn Body>,
+    /// Object to world space.
+    transform: Transform,
+    /// World to object space.
+    inverse: Transform,
+    bounding_box: Aabb,
+}
+
+impl Instance {
+    pub fn new(body: Arc<dyn Body>, transform: Transform) -> Self {
+        let bounding_box = body.bounding_box();
+        let bounding_box = if bounding_box.is_bounded() { transform.bounding_box(&bounding_box) } else { aabb::UNIVERSE };
+
+        Self { body, transform, inverse: transform.inverse(), bounding_box }
+    }
+
+    pub fn body(&self) -> &Arc<dyn Body> {
+        &self.body
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn to_world(&self, hit_record: &mut HitRecord) {
+        hit_record.point = self.transform.point(&hit_record.point);
+        hit_record.normal = self.transform.normal(&hit_record.normal).unit();
+    }
+}
+
+impl Body for Instance {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        if !self.body.hit(&self.inverse.ray(ray), t, hit_record) {
+            return false
+        }
+
+        self.to_world(hit_record);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn spans(&self, ray: &Ray, t: Interval) -> Vec<Span> {
+        let mut spans = self.body.spans(&self.inverse.ray(ray), t);
+
+        for span in &mut spans {
+            self.to_world(&mut span.entry);
+            self.to_world(&mut span.exit);
+        }
+
+        spans
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        self.body.transmittance(&self.inverse.ray(ray), t)
+    }
+
+    /// The density of the body in its own space, scaled by how much the
+    /// transform stretches solid angle around 'direction'.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let object_direction = self.inverse.vector(direction);
+        let pdf = self.body.pdf_value(&self.inverse.point(origin), &object_direction);
+        if pdf == 0.0 {
+            return 0.0
+        }
+
+        // A linear map 'A' grows solid angle around a unit direction 'ω' by
+        // |det A| / |Aω|³.
+        let stretch = object_direction.mag() / direction.mag();
+
+        pdf * self.inverse.determinant().abs() / stretch.powi(3)
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        self.transform.vector(&self.body.random_direction(&self.inverse.point(origin)))
+    }
+
+    /// Adds the lights of the body, each placed with the same transform.
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        let mut body_lights = BodyList::new();
+        self.body.clone().collect_lights(&mut body_lights);
+
+        for light in body_lights.bodies() {
+            lights.push(Arc::new(Instance::new(light.clone(), self.transform)));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::Aabb, material::Material, random};
+
+use super::{Body, HitRecord, body_list::BodyList};
+
+/// Smoke, fog or anything else of the same 'density' throughout the inside
+/// of a closed 'boundary'. Rays scatter at exponentially distributed distances
+/// by the phase function 'phase', like 'Isotropic'.
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Body>,
+    /// Chance per unit of distance of a ray scattering.
+    pub density: f32,
+    pub phase: Arc<dyn Material>,
+}
+
+impl Body for ConstantMedium {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let length = ray.direction.mag();
+
+        // Distances are memoryless, so each stretch inside is sampled afresh.
+        for span in self.boundary.spans(ray, t) {
+            let (start, end) = (span.entry.t.max(t.min), span.exit.t.min(t.max));
+
+            if let Some(root) = scatter_distance(self.density, start, end, length) {
+                *hit_record = scatter_record(ray, root, self.phase.clone());
+                return true
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        let inside: f32 = self.boundary.spans(ray, t).iter()
+            .map(|span| (span.exit.t.min(t.max) - span.entry.t.max(t.min)).max(0.0))
+            .sum();
+
+        (-self.density * inside * ray.direction.mag()).exp()
+    }
+}
+
+/// Fog filling the space between 'bodies', up to the first body a ray hits.
+/// Rays that leave the scene see the environment clear of it, so it adds
+/// depth towards the horizon without hiding the sky or the sun.
+pub struct Fog {
+    pub bodies: Arc<dyn Body>,
+    pub density: f32,
+    pub phase: Arc<dyn Material>,
+}
+
+impl Body for Fog {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let hit = self.bodies.hit(ray, t, hit_record);
+
+        // Rays that go on forever leave the fog behind.
+        let end = if hit { hit_record.t } else { t.max };
+        if end.is_infinite() {
+            return hit
+        }
+
+        match scatter_distance(self.density, t.min, end, ray.direction.mag()) {
+            Some(root) => {
+                *hit_record = scatter_record(ray, root, self.phase.clone());
+                true
+            },
+            None => hit,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bodies.bounding_box()
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        let transmittance = self.bodies.transmittance(ray, t);
+        if t.max.is_infinite() {
+            return transmittance
+        }
+
+        transmittance * (-self.density * (t.max - t.min) * ray.direction.mag()).exp()
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        self.bodies.clone().collect_lights(lights)
+    }
+}
+
+/// Where between 'start' and 'end' a ray whose direction is 'length' long
+/// scatters in a medium of 'density', if it does at all.
+fn scatter_distance(density: f32, start: f32, end: f32, length: f32) -> Option<f32> {
+    if end <= start {
+        return None
+    }
+
+    let distance = -(1.0 - random()).ln() / density;
+
+    Some(start + distance / length).filter(|&root| root < end)
+}
+
+/// Hit where a ray scatters inside a medium at 'root', by 'phase'.
+pub fn scatter_record(ray: &Ray, root: f32, phase: Arc<dyn Material>) -> HitRecord {
+    // Phase functions only depend on the direction of the ray, not on a surface.
+    HitRecord {
+        point: ray.at(root),
+        normal: Vec3D::x_unit(),
+        material: phase,
+        t: root,
+        front_face: true,
+        ..HitRecord::new()
+    }
+}
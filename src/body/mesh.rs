@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::Aabb, material::Material, random};
+
+use super::{
+    Body, HitRecord,
+    bodies::{Triangle, random_point_in_triangle, area_to_solid_angle},
+    body_list::BodyList,
+    bvh::{BvhNode, SplitMethod},
+};
+
+/// Vertex attributes shared by every triangle of one or more meshes.
+#[derive(Default)]
+pub struct MeshBuffers {
+    pub positions: Vec<Vec3D>,
+    pub normals: Vec<Vec3D>,
+    pub uvs: Vec<(f32, f32)>,
+}
+
+/// Indices into 'MeshBuffers' for one corner of a triangle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshVertex {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub uv: Option<usize>,
+}
+
+impl MeshVertex {
+    pub fn new(position: usize) -> Self {
+        Self { position, normal: None, uv: None }
+    }
+}
+
+pub type MeshFace = [MeshVertex; 3];
+
+struct MeshData {
+    buffers: Arc<MeshBuffers>,
+    faces: Vec<MeshFace>,
+    material: Arc<dyn Material>,
+}
+
+/// Indexed triangle mesh. Triangles with normals on all three vertices are
+/// smooth shaded by interpolating them.
+pub struct TriangleMesh {
+    data: Arc<MeshData>,
+    bvh: BvhNode,
+    /// Running total of the face areas, for picking faces in proportion to their area.
+    cumulative_areas: Vec<f32>,
+}
+
+impl TriangleMesh {
+    /// Panics if a face refers to a vertex attribute outside 'buffers'.
+    pub fn new(buffers: Arc<MeshBuffers>, faces: Vec<MeshFace>, material: Arc<dyn Material>) -> Self {
+        for vertex in faces.iter().flatten() {
+            assert!(vertex.position < buffers.positions.len(), "mesh position index out of range");
+            assert!(vertex.normal.is_none_or(|n| n < buffers.normals.len()), "mesh normal index out of range");
+            assert!(vertex.uv.is_none_or(|n| n < buffers.uvs.len()), "mesh uv index out of range");
+        }
+
+        let data = Arc::new(MeshData { buffers, faces, material });
+
+        let mut triangles = BodyList::new();
+        for index in 0..data.faces.len() {
+            triangles.push(Arc::new(MeshTriangle { data: data.clone(), index }));
+        }
+
+        let bvh = BvhNode::new(&triangles, SplitMethod::SurfaceAreaHeuristic);
+
+        let cumulative_areas = data.faces.iter()
+            .scan(0.0, |total, face| {
+                let [a, b, c] = face.map(|vertex| data.buffers.positions[vertex.position]);
+                *total += 0.5 * (b - a).cross(&(c - a)).mag();
+                Some(*total)
+            })
+            .collect();
+
+        Self { data, bvh, cumulative_areas }
+    }
+
+    pub fn buffers(&self) -> &Arc<MeshBuffers> {
+        &self.data.buffers
+    }
+
+    pub fn faces(&self) -> &[MeshFace] {
+        &self.data.faces
+    }
+
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Body for TriangleMesh {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        self.bvh.hit(ray, t, hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    /// Uniform over the surface area of the mesh, converted to solid angle.
+    /// Uses the shading normal, so it is exact for flat shaded meshes only.
+    fn pdf_value(&self, origin: &Vec3D, direction: &Vec3D) -> f32 {
+        let mut hit_record = HitRecord::new();
+        if self.area() <= 0.0 || !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f32::INFINITY), &mut hit_record) {
+            return 0.0
+        }
+
+        area_to_solid_angle(1.0 / self.area(), hit_record.t * direction.mag(), hit_record.normal.dot(&direction.unit()))
+    }
+
+    fn random_direction(&self, origin: &Vec3D) -> Vec3D {
+        if self.data.faces.is_empty() {
+            return Vec3D::x_unit()
+        }
+
+        let target = random() * self.area();
+        let index = self.cumulative_areas.partition_point(|&area| area <= target).min(self.data.faces.len() - 1);
+        let [a, b, c] = self.data.faces[index].map(|vertex| self.data.buffers.positions[vertex.position]);
+
+        random_point_in_triangle(&a, &b, &c) - *origin
+    }
+
+    fn collect_lights(self: Arc<Self>, lights: &mut BodyList) {
+        if self.data.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+struct MeshTriangle {
+    data: Arc<MeshData>,
+    index: usize,
+}
+
+impl MeshTriangle {
+    fn positions(&self) -> [Vec3D; 3] {
+        let face = &self.data.faces[self.index];
+        let positions = &self.data.buffers.positions;
+
+        [positions[face[0].position], positions[face[1].position], positions[face[2].position]]
+    }
+}
+
+impl Body for MeshTriangle {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let [a, b, c] = self.positions();
+
+        let Some((root, u, v)) = Triangle::intersect(&a, &b, &c, ray, &t) else {
+            return false
+        };
+
+        let face = &self.data.faces[self.index];
+        let buffers = &self.data.buffers;
+        let weights = [1.0 - u - v, u, v];
+
+        hit_record.t = root;
+        hit_record.point = ray.at(root);
+        let outward_normal = (b - a).cross(&(c - a)).unit();
+        hit_record.set_face_normal(ray, &outward_normal);
+
+        if let [Some(n0), Some(n1), Some(n2)] = face.map(|vertex| vertex.normal) {
+            let normal = (weights[0] * buffers.normals[n0]
+                + weights[1] * buffers.normals[n1]
+                + weights[2] * buffers.normals[n2]).unit();
+
+            hit_record.normal = if hit_record.front_face { normal } else { -normal };
+        }
+
+        (hit_record.u, hit_record.v) = if let [Some(t0), Some(t1), Some(t2)] = face.map(|vertex| vertex.uv) {
+            let uvs = [buffers.uvs[t0], buffers.uvs[t1], buffers.uvs[t2]];
+
+            (
+                weights[0] * uvs[0].0 + weights[1] * uvs[1].0 + weights[2] * uvs[2].0,
+                weights[0] * uvs[0].1 + weights[1] * uvs[1].1 + weights[2] * uvs[2].1,
+            )
+        } else {
+            (u, v)
+        };
+
+        hit_record.barycentric = Vec3D::new(weights[0], weights[1], weights[2]);
+        hit_record.material = self.data.material.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.positions();
+
+        Aabb::enclosing(&Aabb::from_points(&a, &b), &Aabb::from_points(&c, &c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::materials::Lambertian;
+
+    /// Unit square in the plane z = 0 made of two triangles, with normals
+    /// leaning towards -x on the left edge and +x on the right one.
+    fn square(normals: bool) -> TriangleMesh {
+        let vertex = |position, normal| MeshVertex { position, normal: Some(normal).filter(|_| normals), uv: Some(position) };
+
+        let buffers = MeshBuffers {
+            positions: vec![Vec3D::zero(), Vec3D::x_unit(), Vec3D::new(1.0, 1.0, 0.0), Vec3D::y_unit()],
+            normals: vec![Vec3D::new(-1.0, 0.0, 1.0).unit(), Vec3D::new(1.0, 0.0, 1.0).unit()],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+        };
+        let faces = vec![
+            [vertex(0, 0), vertex(1, 1), vertex(2, 1)],
+            [vertex(0, 0), vertex(2, 1), vertex(3, 0)],
+        ];
+
+        TriangleMesh::new(Arc::new(buffers), faces, Arc::new(Lambertian::new(Vec3D::one())))
+    }
+
+    fn hit(mesh: &TriangleMesh, x: f32, y: f32) -> HitRecord {
+        let mut hit_record = HitRecord::new();
+        let ray = Ray::new(&Vec3D::new(x, y, 1.0), &-Vec3D::z_unit());
+        assert!(mesh.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut hit_record));
+
+        hit_record
+    }
+
+    #[test]
+    fn interpolates_normals_and_uvs() {
+        let mesh = square(true);
+
+        let middle = hit(&mesh, 0.5, 0.25);
+        assert!((middle.normal - Vec3D::z_unit()).mag() < 1e-4, "{:?}", middle.normal);
+        assert!((middle.u - 0.5).abs() < 1e-4 && (middle.v - 0.25).abs() < 1e-4);
+
+        assert!(hit(&mesh, 0.9, 0.5).normal.x > 0.0);
+        assert!(hit(&mesh, 0.1, 0.5).normal.x < 0.0);
+    }
+
+    #[test]
+    fn flat_faces_use_the_geometric_normal() {
+        let hit_record = hit(&square(false), 0.9, 0.5);
+
+        assert!((hit_record.normal - Vec3D::z_unit()).mag() < 1e-6);
+    }
+
+    #[test]
+    fn sums_face_areas() {
+        assert!((square(false).area() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "mesh position index out of range")]
+    fn rejects_faces_outside_the_buffers() {
+        let buffers = MeshBuffers { positions: vec![Vec3D::zero(); 2], ..Default::default() };
+        let face = [MeshVertex::new(0), MeshVertex::new(1), MeshVertex::new(2)];
+
+        TriangleMesh::new(Arc::new(buffers), vec![face], Arc::new(Lambertian::new(Vec3D::one())));
+    }
+}
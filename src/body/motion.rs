@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::{self, Aabb}, transform::Transform};
+
+use super::{Body, HitRecord, Span};
+
+/// Placement of a moving body at one moment: scaled, then rotated around x,
+/// y and z in turn, then translated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3D,
+    /// Degrees around x, y and z. Turns of more than 360 spin the body further.
+    pub rotation: Vec3D,
+    pub scale: Vec3D,
+}
+
+impl Keyframe {
+    /// Leaves the body where it is at 'time'.
+    pub fn new(time: f32) -> Self {
+        Self { time, translation: Vec3D::zero(), rotation: Vec3D::zero(), scale: Vec3D::one() }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3D) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Vec3D) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3D) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn transform(&self) -> Transform {
+        let rotations = [(Vec3D::x_unit(), self.rotation.x), (Vec3D::y_unit(), self.rotation.y), (Vec3D::z_unit(), self.rotation.z)];
+
+        rotations.into_iter()
+            .filter(|&(_, degrees)| degrees != 0.0)
+            .fold(Transform::scaling(&self.scale), |transform, (axis, degrees)| transform.then(&Transform::rotation(&axis, degrees)))
+            .then(&Transform::translation(&self.translation))
+    }
+
+    /// Each part of the placement blended separately, 'fraction' of the way to 'next'.
+    fn lerp(&self, next: &Keyframe, fraction: f32) -> Keyframe {
+        let lerp = |a: Vec3D, b: Vec3D| a + fraction * (b - a);
+
+        Keyframe {
+            time: self.time + fraction * (next.time - self.time),
+            translation: lerp(self.translation, next.translation),
+            rotation: lerp(self.rotation, next.rotation),
+            scale: lerp(self.scale, next.scale),
+        }
+    }
+}
+
+/// Body moving through 'keyframes' over time, for motion blur. Rays see it
+/// placed between the keyframes around their time, and held at the first or
+/// last before or after them. Moving lights are not sampled directly, since
+/// light sampling has no notion of time, but are still found by scattering.
+pub struct Motion {
+    body: Arc<dyn Body>,
+    keyframes: Vec<Keyframe>,
+    bounding_box: Aabb,
+}
+
+impl Motion {
+    /// 'keyframes' must not be empty.
+    pub fn new(body: Arc<dyn Body>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "motion needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let object_box = body.bounding_box();
+        let bounding_box = if !object_box.is_bounded() {
+            aabb::UNIVERSE
+        } else {
+            keyframes.windows(2).fold(keyframes[0].transform().bounding_box(&object_box), |bounding_box, pair| {
+                Aabb::enclosing(&bounding_box, &Self::sweep(&object_box, &pair[0], &pair[1]))
+            })
+        };
+
+        Self { body, keyframes, bounding_box }
+    }
+
+    /// Moves 'body' in a straight line by 'offset' from 'start' to 'end'.
+    pub fn linear(body: Arc<dyn Body>, offset: Vec3D, start: f32, end: f32) -> Self {
+        Self::new(body, vec![Keyframe::new(start), Keyframe::new(end).with_translation(offset)])
+    }
+
+    pub fn body(&self) -> &Arc<dyn Body> {
+        &self.body
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Box enclosing everywhere 'object_box' passes from 'a' to 'b'.
+    fn sweep(object_box: &Aabb, a: &Keyframe, b: &Keyframe) -> Aabb {
+        // Without turning, every point moves in a straight line, so the boxes
+        // at both ends enclose the whole way.
+        if a.rotation == b.rotation {
+            return Aabb::enclosing(&a.transform().bounding_box(object_box), &b.transform().bounding_box(object_box))
+        }
+
+        // Turning, the body stays within the sphere around the origin reaching
+        // its furthest corner, scaled as much as it is anywhere on the way.
+        let Aabb { x, y, z } = object_box;
+        let corner = Vec3D::new(x.min.abs().max(x.max.abs()), y.min.abs().max(y.max.abs()), z.min.abs().max(z.max.abs()));
+        let scale = [a.scale, b.scale].iter().map(|s| s.x.abs().max(s.y.abs()).max(s.z.abs())).fold(0.0, f32::max);
+        let radius = Vec3D::one() * (corner.mag() * scale);
+
+        Aabb::enclosing(
+            &Aabb::from_points(&(a.translation - radius), &(a.translation + radius)),
+            &Aabb::from_points(&(b.translation - radius), &(b.translation + radius)),
+        )
+    }
+
+    /// Object to world space at 'time'.
+    fn transform(&self, time: f32) -> Transform {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+
+        let keyframe = match next {
+            0 => keyframes[0],
+            n if n == keyframes.len() => keyframes[n - 1],
+            n => {
+                let (a, b) = (&keyframes[n - 1], &keyframes[n]);
+                a.lerp(b, (time - a.time) / (b.time - a.time))
+            },
+        };
+
+        keyframe.transform()
+    }
+
+    fn to_world(hit_record: &mut HitRecord, transform: &Transform) {
+        hit_record.point = transform.point(&hit_record.point);
+        hit_record.normal = transform.normal(&hit_record.normal).unit();
+    }
+}
+
+impl Body for Motion {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        if !self.bounding_box.hit(ray, t) {
+            return false
+        }
+
+        let transform = self.transform(ray.time);
+        if !self.body.hit(&transform.inverse().ray(ray), t, hit_record) {
+            return false
+        }
+
+        Self::to_world(hit_record, &transform);
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn spans(&self, ray: &Ray, t: Interval) -> Vec<Span> {
+        let transform = self.transform(ray.time);
+        let mut spans = self.body.spans(&transform.inverse().ray(ray), t);
+
+        for span in &mut spans {
+            Self::to_world(&mut span.entry, &transform);
+            Self::to_world(&mut span.exit, &transform);
+        }
+
+        spans
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        if !self.bounding_box.hit(ray, t) {
+            return 1.0
+        }
+
+        self.body.transmittance(&self.transform(ray.time).inverse().ray(ray), t)
+    }
+}
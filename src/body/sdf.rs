@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::Aabb, material::Material, sdf::Sdf};
+
+use super::{Body, HitRecord, bodies::Sphere};
+
+/// Surface where a signed distance field is zero, found by sphere tracing.
+pub struct SdfBody {
+    pub sdf: Arc<dyn Sdf>,
+    pub material: Arc<dyn Material>,
+    /// Steps a ray may take before it counts as missing.
+    pub max_steps: usize,
+    /// Distance from the surface within which a ray counts as hitting it.
+    pub epsilon: f32,
+}
+
+impl SdfBody {
+    pub fn new(sdf: Arc<dyn Sdf>, material: Arc<dyn Material>) -> Self {
+        Self { sdf, material, max_steps: 256, epsilon: 0.0001 }
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Gradient of the field by central differences on a tetrahedron.
+    fn normal(&self, point: &Vec3D) -> Vec3D {
+        let h = self.epsilon;
+
+        let gradient = [Vec3D::new(1.0, -1.0, -1.0), Vec3D::new(-1.0, -1.0, 1.0), Vec3D::new(-1.0, 1.0, -1.0), Vec3D::new(1.0, 1.0, 1.0)]
+            .into_iter()
+            .fold(Vec3D::zero(), |gradient, k| gradient + k * self.sdf.distance(&(*point + k * h)));
+
+        if gradient.is_near_zero() { Vec3D::y_unit() } else { gradient.unit() }
+    }
+}
+
+impl Body for SdfBody {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let Some(clipped) = self.sdf.bounding_box().clip(ray, t) else {
+            return false
+        };
+
+        // Distances are along the ray, the parameter in units of its direction.
+        let length = ray.direction.mag();
+        let mut root = clipped.min;
+        let mut steps = 0;
+
+        // A ray starting on the surface, like one scattered off it, first has
+        // to move away so it does not hit the surface it left. One entering
+        // the bounding box where it touches the surface hits it there.
+        if root == t.min {
+            while self.sdf.distance(&ray.at(root)).abs() < self.epsilon && steps < self.max_steps {
+                root += 2.0 * self.epsilon / length;
+                steps += 1;
+            }
+        }
+
+        // Surfaces touching the bounding box, like the far side of a sphere, lie
+        // right at its end, and the step onto them may round past it.
+        let end = clipped.max + self.epsilon / length;
+
+        while steps < self.max_steps && root <= end {
+            let distance = self.sdf.distance(&ray.at(root));
+
+            if distance.abs() < self.epsilon {
+                hit_record.t = root;
+                hit_record.point = ray.at(root);
+                let outward_normal = self.normal(&hit_record.point);
+                hit_record.set_face_normal(ray, &outward_normal);
+                hit_record.material = self.material.clone();
+                (hit_record.u, hit_record.v) = Sphere::uv(&outward_normal);
+
+                return true
+            }
+
+            // Works from inside too, where the distance is negative.
+            root += distance.abs() / length;
+            steps += 1;
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sdf.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::materials::Lambertian, sdf::primitives::SphereSdf};
+
+    const ALL: Interval = Interval::new(0.001, f32::INFINITY);
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Vec3D::one()))
+    }
+
+    fn sphere_body() -> SdfBody {
+        SdfBody::new(Arc::new(SphereSdf { center: Vec3D::new(1.0, 0.0, -1.0), radius: 2.0 }), material())
+    }
+
+    fn hit(body: &dyn Body, ray: &Ray) -> Option<HitRecord> {
+        let mut hit_record = HitRecord::new();
+        body.hit(ray, ALL, &mut hit_record).then_some(hit_record)
+    }
+
+    #[test]
+    fn hits_like_an_analytic_sphere() {
+        let sdf = sphere_body();
+        let sphere = Sphere { center: Vec3D::new(1.0, 0.0, -1.0), radius: 2.0, material: material() };
+
+        for (origin, direction) in [
+            (Vec3D::new(1.0, 0.0, 10.0), -Vec3D::z_unit()),
+            (Vec3D::new(-6.0, 1.0, 0.5), Vec3D::new(1.0, 0.0, -0.2)),
+            // Not normalized, so the ray parameter differs from the distance.
+            (Vec3D::new(5.0, 5.0, 5.0), Vec3D::new(-2.0, -2.5, -3.0)),
+            // From inside.
+            (Vec3D::new(1.0, 0.5, -1.0), Vec3D::y_unit()),
+        ] {
+            let ray = Ray::new(&origin, &direction);
+            let (expected, actual) = (hit(&sphere, &ray).unwrap(), hit(&sdf, &ray).unwrap());
+
+            assert!((actual.t - expected.t).abs() < 1e-3, "{} is not {}", actual.t, expected.t);
+            assert!((actual.normal - expected.normal).mag() < 1e-2, "{:?} is not {:?}", actual.normal, expected.normal);
+            assert_eq!(actual.front_face, expected.front_face);
+        }
+
+        assert!(hit(&sdf, &Ray::new(&Vec3D::new(4.0, 0.0, 10.0), &-Vec3D::z_unit())).is_none());
+    }
+
+    #[test]
+    fn leaves_the_surface_it_starts_on() {
+        let sdf = sphere_body();
+        let ray = Ray::new(&Vec3D::new(1.0, 2.0, -1.0), &Vec3D::y_unit());
+
+        assert!(hit(&sdf, &ray).is_none());
+    }
+
+    #[test]
+    fn misses_when_out_of_steps() {
+        // Grazing rays approach the surface slowly, so they need many steps.
+        let ray = Ray::new(&Vec3D::new(-20.0, 1.99, -1.0), &Vec3D::x_unit());
+
+        assert!(hit(&sphere_body(), &ray).is_some());
+        assert!(hit(&sphere_body().with_max_steps(4), &ray).is_none());
+    }
+
+    #[test]
+    fn hits_within_epsilon_of_the_surface() {
+        let ray = Ray::new(&Vec3D::new(1.0, 0.0, 10.0), &-Vec3D::z_unit());
+        let hit_record = hit(&sphere_body().with_epsilon(0.1), &ray).unwrap();
+
+        // The surface is at t = 9.
+        assert!(hit_record.t > 8.9 && hit_record.t <= 9.0, "{}", hit_record.t);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use crate::{vector::Vec3D, ray::Ray, interval::Interval, aabb::Aabb, material::{Material, ScatterSample}, random};
+
+use super::{Body, HitRecord, medium::scatter_record};
+
+/// Dense 3D grid of values, like densities or temperatures, sampled at the
+/// centers of its cells.
+pub struct VoxelGrid {
+    size: [usize; 3],
+    /// x varies fastest, then y, then z.
+    values: Vec<f32>,
+    max: f32,
+}
+
+impl VoxelGrid {
+    /// 'values' holds 'size[0] * size[1] * size[2]' values, x varying fastest.
+    /// No size may be zero.
+    pub fn new(size: [usize; 3], values: Vec<f32>) -> Self {
+        assert!(!size.contains(&0), "voxel grid must not be empty");
+        assert!(value_count(size) == Some(values.len()), "voxel grid of {:?} needs one value per cell, found {}", size, values.len());
+
+        let max = values.iter().copied().fold(0.0, f32::max);
+
+        Self { size, values, max }
+    }
+
+    /// Loads a grid from plain text (.grid), three sizes followed by the
+    /// values separated by whitespace, or from binary (.raw), the three sizes
+    /// as little endian 32-bit integers followed by the values as little
+    /// endian 32-bit floats. Values are in the order of 'new'.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let (size, values) = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("grid") => decode_text(&fs::read_to_string(path)?)?,
+            Some("raw") => decode_binary(&fs::read(path)?)?,
+            _ => return Err(invalid_data(&format!("{}: unsupported grid format, expected .grid or .raw", path.display()))),
+        };
+
+        if size.contains(&0) {
+            return Err(invalid_data(&format!("{}: grid is empty", path.display())))
+        }
+        if values.iter().any(|value| !value.is_finite() || *value < 0.0) {
+            return Err(invalid_data(&format!("{}: grid values must be finite and not negative", path.display())))
+        }
+
+        Ok(Self::new(size, values))
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    /// The largest value in the grid.
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Trilinear interpolation at 'point' in [0, 1]³, clamped to the outermost
+    /// cell centers.
+    pub fn value(&self, point: &Vec3D) -> f32 {
+        let [nx, ny, nz] = self.size;
+
+        // Lower cell index and weight of the upper one along each axis.
+        let split = |p: f32, n: usize| -> (usize, usize, f32) {
+            let x = (p * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+
+        let (x0, x1, tx) = split(point.x, nx);
+        let (y0, y1, ty) = split(point.y, ny);
+        let (z0, z1, tz) = split(point.z, nz);
+
+        let at = |x: usize, y: usize, z: usize| self.values[x + nx * (y + ny * z)];
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        lerp(
+            lerp(lerp(at(x0, y0, z0), at(x1, y0, z0), tx), lerp(at(x0, y1, z0), at(x1, y1, z0), tx), ty),
+            lerp(lerp(at(x0, y0, z1), at(x1, y0, z1), tx), lerp(at(x0, y1, z1), at(x1, y1, z1), tx), ty),
+            tz,
+        )
+    }
+}
+
+/// Smoke, clouds or fire whose density varies through a box, given by a
+/// 'VoxelGrid' stretched over it. Rays scatter by delta tracking against the
+/// densest point, and shadow rays estimate how much light gets through by
+/// ratio tracking.
+pub struct GridVolume {
+    grid: Arc<VoxelGrid>,
+    bounds: Aabb,
+    /// Chance per unit of distance of a ray scattering where the grid is one.
+    density: f32,
+    phase: Arc<dyn Material>,
+    /// Grid and color of light given off, like the temperature of fire.
+    emission: Option<(Arc<VoxelGrid>, Vec3D)>,
+}
+
+impl GridVolume {
+    /// Fills the box with corners 'a' and 'b' with 'grid'.
+    pub fn new(grid: Arc<VoxelGrid>, a: &Vec3D, b: &Vec3D, density: f32, phase: Arc<dyn Material>) -> Self {
+        Self { grid, bounds: Aabb::from_points(a, b), density, phase, emission: None }
+    }
+
+    /// Makes every place a ray scatters glow with 'color' times the value of
+    /// 'grid' there, so the volume is brightest where it is both dense and hot.
+    pub fn with_emission(mut self, grid: Arc<VoxelGrid>, color: Vec3D) -> Self {
+        self.emission = Some((grid, color));
+        self
+    }
+
+    /// 'point' in the [0, 1]³ coordinates of the grids.
+    fn local(&self, point: &Vec3D) -> Vec3D {
+        let Aabb { x, y, z } = self.bounds;
+        Vec3D::new((point.x - x.min) / x.size(), (point.y - y.min) / y.size(), (point.z - z.min) / z.size())
+    }
+
+    fn majorant(&self) -> f32 {
+        self.density * self.grid.max()
+    }
+
+    /// Distance to the next tentative collision in the parameter of a ray
+    /// whose direction is 'length' long.
+    fn step(&self, length: f32) -> f32 {
+        -(1.0 - random()).ln() / (self.majorant() * length)
+    }
+}
+
+impl Body for GridVolume {
+    fn hit(&self, ray: &Ray, t: Interval, hit_record: &mut HitRecord) -> bool {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return false
+        }
+
+        let Some(t) = self.bounds.clip(ray, t) else {
+            return false
+        };
+
+        let length = ray.direction.mag();
+        let mut root = t.min;
+
+        loop {
+            root += self.step(length);
+            if root >= t.max {
+                return false
+            }
+
+            // Collisions with the difference up to the majorant are null and skipped.
+            let point = ray.at(root);
+            let local = self.local(&point);
+            if random() * majorant >= self.density * self.grid.value(&local) {
+                continue
+            }
+
+            let material: Arc<dyn Material> = match &self.emission {
+                Some((grid, color)) => Arc::new(Glow { phase: self.phase.clone(), radiance: *color * grid.value(&local) }),
+                None => self.phase.clone(),
+            };
+
+            *hit_record = scatter_record(ray, root, material);
+
+            return true
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn transmittance(&self, ray: &Ray, t: Interval) -> f32 {
+        let majorant = self.majorant();
+        let Some(t) = self.bounds.clip(ray, t).filter(|_| majorant > 0.0) else {
+            return 1.0
+        };
+
+        let length = ray.direction.mag();
+        let mut root = t.min;
+        let mut transmittance = 1.0;
+
+        loop {
+            root += self.step(length);
+            if root >= t.max {
+                return transmittance
+            }
+
+            // Every tentative collision lets through the share that is null.
+            transmittance *= 1.0 - self.density * self.grid.value(&self.local(&ray.at(root))) / majorant;
+
+            // Little light is left, so the estimate ends at random, scaling up the survivors.
+            if transmittance < 0.1 {
+                if random() >= transmittance {
+                    return 0.0
+                }
+                transmittance = 1.0;
+            }
+        }
+    }
+}
+
+/// Phase function of a glowing part of a 'GridVolume'.
+struct Glow {
+    phase: Arc<dyn Material>,
+    radiance: Vec3D,
+}
+
+impl Material for Glow {
+    fn sample(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
+        self.phase.sample(ray_in, hit_record)
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> Vec3D {
+        self.phase.eval(ray_in, hit_record, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> f32 {
+        self.phase.pdf(ray_in, hit_record, direction)
+    }
+
+    fn emit(&self, _hit_record: &HitRecord) -> Vec3D {
+        self.radiance
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn decode_text(source: &str) -> io::Result<([usize; 3], Vec<f32>)> {
+    let mut tokens = source.split_whitespace();
+
+    let mut size = [0; 3];
+    for n in &mut size {
+        let token = tokens.next().ok_or_else(|| invalid_data("unexpected end of grid header"))?;
+        *n = token.parse().map_err(|_| invalid_data(&format!("invalid grid size '{}'", token)))?;
+    }
+
+    let values = tokens
+        .map(|token| token.parse::<f32>().map_err(|_| invalid_data(&format!("invalid grid value '{}'", token))))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    check_count(size, values)
+}
+
+fn decode_binary(bytes: &[u8]) -> io::Result<([usize; 3], Vec<f32>)> {
+    let header = bytes.get(..12).ok_or_else(|| invalid_data("unexpected end of grid header"))?;
+
+    let mut size = [0; 3];
+    for (n, b) in size.iter_mut().zip(header.chunks_exact(4)) {
+        *n = u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize;
+    }
+
+    let values = bytes[12..].chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    check_count(size, values)
+}
+
+/// Number of cells of a grid of 'size', if it fits in memory at all.
+fn value_count(size: [usize; 3]) -> Option<usize> {
+    size.iter().try_fold(1usize, |count, &n| count.checked_mul(n))
+}
+
+fn check_count(size: [usize; 3], values: Vec<f32>) -> io::Result<([usize; 3], Vec<f32>)> {
+    let Some(expected) = value_count(size) else {
+        return Err(invalid_data(&format!("grid of {}x{}x{} is too large", size[0], size[1], size[2])))
+    };
+    if values.len() != expected {
+        return Err(invalid_data(&format!("grid of {}x{}x{} needs {} values, found {}", size[0], size[1], size[2], expected, values.len())))
+    }
+
+    Ok((size, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(size: [u32; 3], values: &[f32]) -> Vec<u8> {
+        size.iter().flat_map(|n| n.to_le_bytes())
+            .chain(values.iter().flat_map(|value| value.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_text_grids() {
+        let (size, values) = decode_text("2 1 1\n0.5 1.5").unwrap();
+
+        assert_eq!(size, [2, 1, 1]);
+        assert_eq!(values, vec![0.5, 1.5]);
+    }
+
+    #[test]
+    fn decodes_binary_grids() {
+        let (size, values) = decode_binary(&binary([1, 2, 1], &[0.25, 4.0])).unwrap();
+
+        assert_eq!(size, [1, 2, 1]);
+        assert_eq!(values, vec![0.25, 4.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(decode_text("2 1").is_err());
+        assert!(decode_text("2 -1 1 0 0").is_err());
+        assert!(decode_text("2 1 1 0.5 x").is_err());
+        assert!(decode_binary(&[0; 11]).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_value_counts() {
+        assert!(decode_text("2 2 1 1 2 3").is_err());
+        assert!(decode_binary(&binary([2, 2, 2], &[1.0; 7])).is_err());
+    }
+
+    #[test]
+    fn rejects_sizes_overflowing_the_count() {
+        let error = decode_binary(&binary([u32::MAX; 3], &[])).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_empty_grids() {
+        VoxelGrid::new([0, 1, 1], vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_missing_values() {
+        VoxelGrid::new([2, 2, 1], vec![1.0; 3]);
+    }
+
+    #[test]
+    fn interpolates_between_cell_centers() {
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 1.0]);
+
+        assert_eq!(grid.max(), 1.0);
+        assert_eq!(grid.value(&Vec3D::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.value(&Vec3D::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.value(&Vec3D::new(1.0, 0.5, 0.5)), 1.0);
+    }
+
+    #[test]
+    fn single_cell_grids_are_constant() {
+        let grid = VoxelGrid::new([1, 1, 1], vec![2.0]);
+
+        assert_eq!(grid.value(&Vec3D::new(0.1, 0.9, 0.5)), 2.0);
+    }
+}
@@ -1,7 +1,11 @@
 use std::ops::{Div, Neg};
 
-use macroquad::{texture::{Image, Texture2D, draw_texture_ex, DrawTextureParams}, color::{Color, BLANK, WHITE}, window::clear_background, text::draw_text, time::get_fps, shapes::{draw_rectangle_ex, DrawRectangleParams}, math::Vec2};
-use crate::{body::Body, vector::Vec3D, ray::Ray, random, degrees_to_radians};
+use macroquad::{texture::{Image, Texture2D, draw_texture_ex, DrawTextureParams}, color::{BLANK, WHITE}, window::clear_background, text::draw_text, time::get_fps, math::Vec2};
+use rayon::prelude::*;
+use crate::{vector::Vec3D, ray::Ray, film::Film, integrator::Integrator, post_process::PostProcess, world::World, random, degrees_to_radians};
+
+/// Width and height in pixels of the blocks rendered in parallel.
+const TILE_SIZE: usize = 16;
 
 pub struct Camera {
     aspect_ratio: f32,
@@ -9,15 +13,13 @@ pub struct Camera {
     image_height: usize,
 
     center: Vec3D,
-    direction: Vec3D,
-    image: Image,
-    texture: Texture2D,
-
-    viewport_origin: Vec3D,
-    viewport_width: f32,
-    viewport_height: f32,
-    viewport_u: Vec3D,
-    viewport_v: Vec3D,
+    film: Film,
+    /// 'film' after post-processing, as drawn and written to files.
+    display_image: Image,
+    post_process: PostProcess,
+    integrator: Integrator,
+    /// Created on first draw, so cameras can render without a window.
+    texture: Option<Texture2D>,
 
     pixel_origin: Vec3D,
     pixel_delta_u: Vec3D,
@@ -32,40 +34,172 @@ pub struct Camera {
     look_to: Vec3D,
     view_up: Vec3D,
 
-    u: Vec3D,
-    v: Vec3D,
-    w: Vec3D,
-
     defocus_angle: f32,
     focus_distance: f32,
 
     defocus_disk_u: Vec3D,
     defocus_disk_v: Vec3D,
 
+    shutter_open: f32,
+    shutter_close: f32,
+
     image_scaling: f32,
 
     frame_count: usize,
 }
 
+/// Parameters a camera is built from. Everything else is derived from these.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    pub aspect_ratio: f32,
+    pub image_width: usize,
+    pub vertical_field_of_view: f32,
+    pub look_from: Vec3D,
+    pub look_to: Vec3D,
+    pub view_up: Vec3D,
+    pub defocus_angle: f32,
+    pub focus_distance: f32,
+    pub samples_per_pixel: usize,
+    pub image_scaling: f32,
+    /// Rays are traced at random times from 'shutter_open' to 'shutter_close',
+    /// blurring bodies in 'Motion'.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            aspect_ratio: 16.0 / 9.0, // ideal aspect ratio
+            image_width: 720,
+            vertical_field_of_view: 90.0,
+            look_from: Vec3D::new(0.0, 0.0, 2.0),
+            look_to: Vec3D::zero(),
+            view_up: Vec3D::y_unit(),
+            defocus_angle: 0.0,
+            focus_distance: 3.0,
+            samples_per_pixel: 100,
+            image_scaling: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+}
+
+/// Fluent construction of a 'Camera', starting from 'CameraSettings::default()'.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CameraBuilder {
+    settings: CameraSettings,
+}
+
+impl CameraBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_settings(settings: CameraSettings) -> Self {
+        Self { settings }
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.settings.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: usize) -> Self {
+        self.settings.image_width = image_width;
+        self
+    }
+
+    /// In degrees.
+    pub fn vertical_field_of_view(mut self, vertical_field_of_view: f32) -> Self {
+        self.settings.vertical_field_of_view = vertical_field_of_view;
+        self
+    }
+
+    pub fn look_from(mut self, look_from: Vec3D) -> Self {
+        self.settings.look_from = look_from;
+        self
+    }
+
+    pub fn look_to(mut self, look_to: Vec3D) -> Self {
+        self.settings.look_to = look_to;
+        self
+    }
+
+    pub fn view_up(mut self, view_up: Vec3D) -> Self {
+        self.settings.view_up = view_up;
+        self
+    }
+
+    /// Cone angle in degrees of rays through each pixel. Zero disables depth of field.
+    pub fn defocus_angle(mut self, defocus_angle: f32) -> Self {
+        self.settings.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_distance(mut self, focus_distance: f32) -> Self {
+        self.settings.focus_distance = focus_distance;
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.settings.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Size of the drawn image relative to the rendered one.
+    pub fn image_scaling(mut self, image_scaling: f32) -> Self {
+        self.settings.image_scaling = image_scaling;
+        self
+    }
+
+    pub fn shutter_open(mut self, shutter_open: f32) -> Self {
+        self.settings.shutter_open = shutter_open;
+        self
+    }
+
+    pub fn shutter_close(mut self, shutter_close: f32) -> Self {
+        self.settings.shutter_close = shutter_close;
+        self
+    }
+
+    pub fn settings(&self) -> CameraSettings {
+        self.settings
+    }
+
+    pub fn build(self) -> Camera {
+        Camera::with_settings(self.settings)
+    }
+}
+
 impl Camera {
     pub fn new() -> Self {
-        let aspect_ratio: f32 = 16.0 / 9.0; // ideal aspect ratio
-        let image_width: usize = 720;
+        Self::with_settings(CameraSettings::default())
+    }
+
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+
+    pub fn with_settings(settings: CameraSettings) -> Self {
+        let aspect_ratio: f32 = settings.aspect_ratio;
+        let image_width: usize = settings.image_width.max(1);
         let image_height: usize = {
             let x = (image_width as f32 / aspect_ratio) as usize;
             if x < 1 { 1 } else { x }
         };
 
-        let look_from = Vec3D::new(0.0, 0.0, 2.0);
-        let look_to = Vec3D::zero();
-        let view_up = Vec3D::y_unit();
+        let look_from = settings.look_from;
+        let look_to = settings.look_to;
+        let view_up = settings.view_up;
 
         let view_direction = look_from - look_to;
 
-        let defocus_angle = 0.0;
-        let focus_distance = 3.0;
+        let defocus_angle = settings.defocus_angle;
+        let focus_distance = settings.focus_distance;
 
-        let vertical_field_of_view: f32 = 90.0;
+        let vertical_field_of_view: f32 = settings.vertical_field_of_view;
         let theta: f32 = degrees_to_radians(vertical_field_of_view);
         let h = theta.div(2.0).tan();
         let viewport_height: f32 = 2.0 * h * focus_distance;
@@ -78,7 +212,7 @@ impl Camera {
         let viewport_u: Vec3D = viewport_width * u;
         let viewport_v: Vec3D = viewport_height.neg() * v;
 
-        let image: Image = {
+        let display_image: Image = {
             let mut image = Image::empty();
 
             image.width = image_width as u16;
@@ -88,10 +222,7 @@ impl Camera {
             image
         };
 
-        let texture: Texture2D = Texture2D::from_image(&image);
-
         let center: Vec3D = look_from;
-        let direction: Vec3D = view_direction.unit();
 
         let pixel_delta_u: Vec3D = viewport_u / image_width as f32;
         let pixel_delta_v: Vec3D = viewport_v / image_height as f32;
@@ -103,19 +234,16 @@ impl Camera {
 
         let pixel_origin: Vec3D = viewport_origin + 0.5 * (pixel_delta_u + pixel_delta_v);
 
-        let samples_per_pixel: usize = 100;
+        let samples_per_pixel: usize = settings.samples_per_pixel.max(1);
 
-        let image_scaling = 1.0;
+        let image_scaling = settings.image_scaling;
 
-        let draw_parameters = {
-            let mut draw_parameters = DrawTextureParams::default();
-
-            draw_parameters.dest_size = Some(Vec2::new(
+        let draw_parameters = DrawTextureParams {
+            dest_size: Some(Vec2::new(
                 image_width as f32 * image_scaling, 
                 image_height as f32 * image_scaling
-            ));
-
-            draw_parameters
+            )),
+            ..Default::default()
         };
 
         let defocus_radius = focus_distance * degrees_to_radians(defocus_angle / 2.0).tan();
@@ -130,15 +258,11 @@ impl Camera {
             image_height,
 
             center,
-            direction,
-            image,
-            texture,
-
-            viewport_origin,
-            viewport_width,
-            viewport_height,
-            viewport_u,
-            viewport_v,
+            film: Film::new(image_width, image_height),
+            display_image,
+            texture: None,
+            post_process: PostProcess::default(),
+            integrator: Integrator::default(),
 
             pixel_origin,
             pixel_delta_u,
@@ -154,21 +278,60 @@ impl Camera {
             look_to,
             view_up,
 
-            u,
-            v,
-            w,
-
             defocus_angle,
             focus_distance,
             defocus_disk_u,
             defocus_disk_v,
 
+            shutter_open: settings.shutter_open,
+            shutter_close: settings.shutter_close,
+
             image_scaling,
 
             frame_count,
         }
     }
 
+    pub fn settings(&self) -> CameraSettings {
+        CameraSettings {
+            aspect_ratio: self.aspect_ratio,
+            image_width: self.image_width,
+            vertical_field_of_view: self.vertical_field_of_view,
+            look_from: self.look_from,
+            look_to: self.look_to,
+            view_up: self.view_up,
+            defocus_angle: self.defocus_angle,
+            focus_distance: self.focus_distance,
+            samples_per_pixel: self.samples_per_pixel,
+            image_scaling: self.image_scaling,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+        }
+    }
+
+    /// Recomputes everything derived from 'settings' and restarts progressive
+    /// accumulation. The display texture is kept if the image size is unchanged.
+    pub fn set_settings(&mut self, settings: CameraSettings) {
+        let texture = self.texture.take();
+        let (image_width, image_height) = (self.image_width, self.image_height);
+        let (post_process, integrator) = (self.post_process, self.integrator);
+
+        *self = Self::with_settings(settings);
+        self.post_process = post_process;
+        self.integrator = integrator;
+
+        if (self.image_width, self.image_height) == (image_width, image_height) {
+            self.texture = texture;
+        }
+    }
+
+    /// Applies 'change' to the current settings, see 'set_settings'.
+    pub fn update_settings(&mut self, change: impl FnOnce(&mut CameraSettings)) {
+        let mut settings = self.settings();
+        change(&mut settings);
+        self.set_settings(settings);
+    }
+
     pub fn get_image_width(&self) -> usize {
         self.image_width
     }
@@ -185,37 +348,109 @@ impl Camera {
         (self.image_height as f32 * self.image_scaling) as usize
     }
 
-    pub fn render(&mut self, world: &dyn Body) {
+    pub fn get_samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    /// Restarts progressive accumulation with the new sample count.
+    pub fn set_samples_per_pixel(&mut self, samples_per_pixel: usize) {
+        self.samples_per_pixel = samples_per_pixel.max(1);
+        self.frame_count = 0;
+        self.film.clear();
+    }
+
+    /// The post-processed image, ready for display or saving.
+    pub fn get_image(&self) -> &Image {
+        &self.display_image
+    }
+
+    /// The accumulated linear radiance.
+    pub fn get_film(&self) -> &Film {
+        &self.film
+    }
+
+    pub fn get_post_process(&self) -> PostProcess {
+        self.post_process
+    }
+
+    /// Takes effect immediately without restarting accumulation.
+    pub fn set_post_process(&mut self, post_process: PostProcess) {
+        self.post_process = post_process;
+        self.post_process.develop(&self.film, &mut self.display_image);
+    }
+
+    pub fn get_integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    /// Restarts progressive accumulation.
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+        self.frame_count = 0;
+        self.film.clear();
+    }
+
+    /// Accumulates one frame and draws the result to the window.
+    pub fn render(&mut self, world: &World) {
         clear_background(BLANK);
 
-        self.frame_count += 1;
-        
-        for y in 0..self.image_height {
-            for x in 0..self.image_width {
-                let mut pixel_color = Vec3D::zero();
+        self.accumulate(world);
 
-                for _ in 0..self.samples_per_pixel {
-                    pixel_color += self.get_ray(x, y).color(world, 0);
-                }
+        let texture = self.texture.get_or_insert_with(|| Texture2D::from_image(&self.display_image));
+        texture.update(&self.display_image);
+
+        draw_texture_ex(texture, 0.0, 0.0, WHITE, self.draw_parameters.clone());
+
+        draw_text(&format!("FPS: {}", get_fps()), 5.0, 20.0, 30.0, WHITE);
+    }
+
+    /// Traces 'samples_per_pixel' more samples for every pixel and adds them
+    /// to the film. Needs no window.
+    pub fn accumulate(&mut self, world: &World) {
+        self.frame_count += 1;
 
-                let pixel_color = pixel_color / self.samples_per_pixel as f32;
+        let tiles: Vec<(usize, usize)> = (0..self.image_height).step_by(TILE_SIZE)
+            .flat_map(|y| (0..self.image_width).step_by(TILE_SIZE).map(move |x| (x, y)))
+            .collect();
 
-                let pixel = self.image.get_pixel(x as u32, y as u32);
+        let rendered_tiles: Vec<Vec<Vec3D>> = tiles.par_iter()
+            .map(|&(x, y)| self.render_tile(world, x, y))
+            .collect();
 
-                let pixel_color = (pixel_color / self.frame_count as f32) 
-                    + (Vec3D::new(pixel.r, pixel.g, pixel.b) * ((self.frame_count - 1) as f32 / self.frame_count as f32));
+        for (&(tile_x, tile_y), colors) in tiles.iter().zip(rendered_tiles) {
+            let tile_width = TILE_SIZE.min(self.image_width - tile_x);
 
-                let color = Color::new(pixel_color.x, pixel_color.y, pixel_color.z, 1.0);
+            for (i, pixel_color) in colors.into_iter().enumerate() {
+                let x = tile_x + i % tile_width;
+                let y = tile_y + i / tile_width;
 
-                self.image.set_pixel(x as u32, y as u32, color);
+                self.film.add_samples(x, y, pixel_color, self.samples_per_pixel as u32);
             }
         }
 
-        self.texture.update(&self.image);
+        self.post_process.develop(&self.film, &mut self.display_image);
+    }
 
-        draw_texture_ex(&self.texture, 0.0, 0.0, WHITE, self.draw_parameters.clone());
+    /// Summed samples for the tile whose top left pixel is ('tile_x', 'tile_y'), row by row.
+    fn render_tile(&self, world: &World, tile_x: usize, tile_y: usize) -> Vec<Vec3D> {
+        let x_end = (tile_x + TILE_SIZE).min(self.image_width);
+        let y_end = (tile_y + TILE_SIZE).min(self.image_height);
 
-        draw_text(&format!("FPS: {}", get_fps()), 5.0, 20.0, 30.0, WHITE);
+        let mut colors = Vec::with_capacity((x_end - tile_x) * (y_end - tile_y));
+
+        for y in tile_y..y_end {
+            for x in tile_x..x_end {
+                let mut pixel_color = Vec3D::zero();
+
+                for _ in 0..self.samples_per_pixel {
+                    pixel_color += self.integrator.radiance(&self.get_ray(x, y), world);
+                }
+
+                colors.push(pixel_color);
+            }
+        }
+
+        colors
     }
 
     fn get_ray(&self, x: usize, y: usize) -> Ray {
@@ -227,7 +462,9 @@ impl Camera {
         let ray_origin = if self.defocus_angle <= 0.0 { self.center } else { self.defocus_disk_sample() };
         let ray_direction = pixel_sample - self.center;
 
-        Ray::new(&ray_origin, &ray_direction)
+        let time = self.shutter_open + random() * (self.shutter_close - self.shutter_open);
+
+        Ray::new(&ray_origin, &ray_direction).with_time(time)
     }
 
     fn pixel_sample_square(&self) -> Vec3D {
@@ -242,4 +479,10 @@ impl Camera {
 
         self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
-}
\ No newline at end of file
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,32 @@
+use crate::vector::Vec3D;
+
+pub mod environments;
+pub mod distribution;
+pub mod sky;
+
+/// Direction towards the environment chosen by 'Environment::sample'.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvironmentSample {
+    /// Unit vector.
+    pub direction: Vec3D,
+    pub radiance: Vec3D,
+    /// Density per unit solid angle of choosing 'direction'.
+    pub pdf: f32,
+}
+
+/// Light arriving from infinitely far away, seen by every ray that escapes the scene.
+pub trait Environment: Send + Sync {
+    /// Radiance arriving along the reverse of 'direction', which need not be normalized.
+    fn radiance(&self, direction: &Vec3D) -> Vec3D;
+
+    /// Picks a direction to sample the environment as a light in, or 'None' if
+    /// it is only found by rays escaping the scene.
+    fn sample(&self) -> Option<EnvironmentSample> {
+        None
+    }
+
+    /// Density per unit solid angle of 'sample' choosing 'direction'.
+    fn pdf(&self, _direction: &Vec3D) -> f32 {
+        0.0
+    }
+}
@@ -0,0 +1,166 @@
+/// Piecewise constant density over [0, 1), proportional to a list of values.
+pub struct Distribution1D {
+    values: Vec<f32>,
+    /// 'cdf[i]' is the probability of landing before piece 'i'; one longer than 'values'.
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    /// Negative values count as zero. If every value is zero the density is uniform.
+    pub fn new(values: Vec<f32>) -> Self {
+        let values: Vec<f32> = values.into_iter().map(|value| value.max(0.0)).collect();
+        let count = values.len().max(1) as f32;
+
+        let mut cdf = Vec::with_capacity(values.len() + 1);
+        cdf.push(0.0);
+        for value in &values {
+            cdf.push(cdf[cdf.len() - 1] + value / count);
+        }
+
+        let integral = cdf[cdf.len() - 1];
+
+        for (i, entry) in cdf.iter_mut().enumerate() {
+            *entry = if integral > 0.0 { *entry / integral } else { i as f32 / count };
+        }
+
+        Self { values, cdf, integral }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Average of the values.
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Maps a uniform 'u' in [0, 1) to a position in [0, 1) with this density,
+    /// returning the position, its density and the index of its piece.
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let index = self.cdf.partition_point(|&entry| entry <= u).clamp(1, self.len()) - 1;
+
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+
+        ((index as f32 + offset.clamp(0.0, 1.0)) / self.len() as f32, self.pdf(index), index)
+    }
+
+    /// Density anywhere within piece 'index'.
+    pub fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 { self.values[index] / self.integral } else { 1.0 }
+    }
+}
+
+/// Piecewise constant density over [0, 1)², proportional to a grid of values
+/// stored row by row. Rows are picked first, then a column within the row.
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(width: usize, height: usize, values: &[f32]) -> Self {
+        assert_eq!(values.len(), width * height, "distribution size mismatch");
+
+        let rows: Vec<Distribution1D> = values.chunks_exact(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(Distribution1D::integral).collect());
+
+        Self { rows, marginal }
+    }
+
+    /// Maps uniform ('u', 'v') to a point with this density, returning the
+    /// point as (column, row) coordinates in [0, 1) and its density.
+    pub fn sample(&self, u: f32, v: f32) -> ((f32, f32), f32) {
+        let (y, row_pdf, row) = self.marginal.sample(v);
+        let (x, column_pdf, _) = self.rows[row].sample(u);
+
+        ((x, y), row_pdf * column_pdf)
+    }
+
+    /// Density at the point with (column, row) coordinates ('x', 'y') in [0, 1).
+    pub fn pdf(&self, x: f32, y: f32) -> f32 {
+        let row = ((y * self.marginal.len() as f32) as usize).min(self.marginal.len() - 1);
+        let row_distribution = &self.rows[row];
+        let column = ((x * row_distribution.len() as f32) as usize).min(row_distribution.len() - 1);
+
+        self.marginal.pdf(row) * row_distribution.pdf(column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn density_integrates_to_one() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+        let integral: f32 = (0..distribution.len()).map(|i| distribution.pdf(i)).sum::<f32>() / distribution.len() as f32;
+
+        assert!((integral - 1.0).abs() < 1e-6);
+        assert_eq!(distribution.integral(), 2.0);
+    }
+
+    #[test]
+    fn samples_follow_the_values() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0]);
+
+        assert_eq!(distribution.sample(0.1).2, 0);
+        assert_eq!(distribution.sample(0.3).2, 1);
+
+        // The first quarter of 'u' maps onto the first half of [0, 1).
+        let (x, pdf, _) = distribution.sample(0.125);
+        assert!((x - 0.25).abs() < 1e-6);
+        assert!((pdf - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn never_samples_zero_values() {
+        let distribution = Distribution1D::new(vec![0.0, 1.0, 0.0]);
+
+        for i in 0..100 {
+            let (x, _, index) = distribution.sample(i as f32 / 100.0);
+            assert_eq!(index, 1);
+            assert!((1.0 / 3.0..2.0 / 3.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn all_zero_values_are_uniform() {
+        let distribution = Distribution1D::new(vec![0.0, -1.0]);
+
+        assert_eq!(distribution.pdf(0), 1.0);
+        assert_eq!(distribution.pdf(1), 1.0);
+        assert_eq!(distribution.sample(0.75).2, 1);
+    }
+
+    #[test]
+    fn density_2d_integrates_to_one() {
+        let (width, height) = (3, 2);
+        let distribution = Distribution2D::new(width, height, &[1.0, 2.0, 0.0, 5.0, 0.5, 0.5]);
+
+        let integral: f32 = (0..height)
+            .flat_map(|y| (0..width).map(move |x| ((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32)))
+            .map(|(x, y)| distribution.pdf(x, y))
+            .sum::<f32>() / (width * height) as f32;
+
+        assert!((integral - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn samples_2d_report_their_density() {
+        let distribution = Distribution2D::new(2, 2, &[1.0, 2.0, 3.0, 4.0]);
+
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7), (0.3, 0.95)] {
+            let ((x, y), pdf) = distribution.sample(u, v);
+            assert!((pdf - distribution.pdf(x, y)).abs() < 1e-5);
+        }
+    }
+}
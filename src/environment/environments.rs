@@ -0,0 +1,444 @@
+use std::{f32::consts::PI, fs, io, path::Path};
+
+use crate::{random, degrees_to_radians};
+
+use super::{*, distribution::Distribution2D};
+
+/// The same radiance from every direction.
+pub struct UniformEnvironment { pub color: Vec3D }
+
+impl UniformEnvironment {
+    pub fn new(color: Vec3D) -> Self {
+        Self { color }
+    }
+}
+
+impl Environment for UniformEnvironment {
+    fn radiance(&self, _direction: &Vec3D) -> Vec3D {
+        self.color
+    }
+}
+
+/// Equirectangular radiance image wrapped around the scene, with +y at the top
+/// row and -x at the left edge. Sampled in proportion to its luminance.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3D>,
+    distribution: Distribution2D,
+    /// Turn around the y axis, in radians.
+    rotation: f32,
+    /// Multiplies every pixel.
+    pub intensity: f32,
+}
+
+impl EnvironmentMap {
+    /// 'pixels' are linear radiance, row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3D>) -> Self {
+        assert_eq!(pixels.len(), width * height, "environment map size mismatch");
+        assert!(width > 0 && height > 0, "environment map is empty");
+
+        // Rows near the poles cover less solid angle, so they are sampled less.
+        let weights: Vec<f32> = pixels.iter().enumerate()
+            .map(|(i, pixel)| {
+                let theta = PI * ((i / width) as f32 + 0.5) / height as f32;
+                pixel.luminance() * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(width, height, &weights);
+
+        Self { width, height, pixels, distribution, rotation: 0.0, intensity: 1.0 }
+    }
+
+    /// Loads a Radiance '.hdr' or a '.pfm' file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+
+        let (width, height, pixels) = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("hdr") => decode_hdr(&bytes)?,
+            Some("pfm") => decode_pfm(&bytes)?,
+            _ => return Err(invalid_data(&format!("{}: unsupported image format, expected .hdr or .pfm", path.display()))),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(invalid_data(&format!("{}: image is empty", path.display())))
+        }
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Turns the map around the y axis by 'degrees'.
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees_to_radians(degrees);
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Image coordinates in [0, 1)² of the unit vector 'direction'.
+    fn direction_to_uv(&self, direction: &Vec3D) -> (f32, f32) {
+        let theta = direction.y.clamp(-1.0, 1.0).acos();
+        let phi = (-direction.z).atan2(direction.x) + PI - self.rotation;
+
+        (phi.rem_euclid(2.0 * PI) / (2.0 * PI), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Vec3D {
+        let theta = v * PI;
+        let phi = u * 2.0 * PI + self.rotation - PI;
+
+        Vec3D::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+    }
+
+    /// Bilinear lookup, wrapping around horizontally.
+    fn lookup(&self, u: f32, v: f32) -> Vec3D {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let texel = |x: i64, y: i64| -> Vec3D {
+            let x = x.rem_euclid(self.width as i64) as usize;
+            let y = y.clamp(0, self.height as i64 - 1) as usize;
+
+            self.pixels[y * self.width + x]
+        };
+
+        let top = (1.0 - tx) * texel(x0, y0) + tx * texel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * texel(x0, y0 + 1) + tx * texel(x0 + 1, y0 + 1);
+
+        (1.0 - ty) * top + ty * bottom
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vec3D) -> Vec3D {
+        let (u, v) = self.direction_to_uv(&direction.unit());
+
+        self.intensity * self.lookup(u, v)
+    }
+
+    fn sample(&self) -> Option<EnvironmentSample> {
+        let ((u, v), uv_pdf) = self.distribution.sample(random(), random());
+        let sin_theta = (v * PI).sin();
+
+        if uv_pdf <= 0.0 || sin_theta <= 0.0 {
+            return None
+        }
+
+        let direction = self.uv_to_direction(u, v);
+
+        Some(EnvironmentSample {
+            direction,
+            radiance: self.intensity * self.lookup(u, v),
+            // The image spans 2π by π radians, and a pixel shrinks with sin(theta).
+            pdf: uv_pdf / (2.0 * PI * PI * sin_theta),
+        })
+    }
+
+    fn pdf(&self, direction: &Vec3D) -> f32 {
+        let (u, v) = self.direction_to_uv(&direction.unit());
+        let sin_theta = (v * PI).sin();
+
+        if sin_theta <= 0.0 {
+            return 0.0
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_large() -> io::Error {
+    invalid_data("image is too large")
+}
+
+/// Number of pixels of an image of 'width' by 'height', if it fits in memory at all.
+fn pixel_count(width: usize, height: usize) -> io::Result<usize> {
+    width.checked_mul(height).ok_or_else(too_large)
+}
+
+/// Reads the next line without its '\n', advancing 'position' past it.
+fn next_line<'a>(bytes: &'a [u8], position: &mut usize) -> io::Result<&'a [u8]> {
+    if *position >= bytes.len() {
+        return Err(invalid_data("unexpected end of header"))
+    }
+
+    let start = *position;
+    let end = bytes[start..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |length| start + length);
+    *position = end + 1;
+
+    Ok(&bytes[start..end])
+}
+
+/// Reads Radiance RGBE images, flat or run length encoded, stored top down.
+fn decode_hdr(bytes: &[u8]) -> io::Result<(usize, usize, Vec<Vec3D>)> {
+    let mut position = 0;
+
+    if !next_line(bytes, &mut position)?.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance HDR file"))
+    }
+
+    loop {
+        let line = next_line(bytes, &mut position)?;
+
+        if line.is_empty() {
+            break
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("only RGBE HDR files are supported"))
+        }
+    }
+
+    let resolution = String::from_utf8_lossy(next_line(bytes, &mut position)?).into_owned();
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["-Y", height, "+X", width] => (
+            height.parse::<usize>().map_err(|_| invalid_data("invalid HDR height"))?,
+            width.parse::<usize>().map_err(|_| invalid_data("invalid HDR width"))?,
+        ),
+        _ => return Err(invalid_data(&format!("unsupported HDR orientation '{}'", resolution))),
+    };
+
+    // Empty scanlines consume nothing, so a huge height would spin without ever running out of data.
+    if width == 0 || height == 0 {
+        return Err(invalid_data("HDR image has no pixels"))
+    }
+
+    let truncated = || invalid_data("unexpected end of HDR data");
+
+    // Scanlines this wide cannot be run length encoded, so they must all be there.
+    if width >= 32768 && width > bytes.len() / 4 {
+        return Err(truncated())
+    }
+
+    // Run length encoding lets small files claim huge images, so only trust the size as far as the data goes.
+    let mut pixels = Vec::with_capacity(pixel_count(width, height)?.min(bytes.len()));
+    let mut scanline = vec![[0u8; 4]; width];
+
+    for _ in 0..height {
+        let header = bytes.get(position..position + 4).ok_or_else(truncated)?;
+        let is_run_length_encoded = (8..32768).contains(&width)
+            && header[0] == 2 && header[1] == 2 && header[2] & 0x80 == 0;
+
+        if is_run_length_encoded {
+            if ((header[2] as usize) << 8 | header[3] as usize) != width {
+                return Err(invalid_data("HDR scanline width mismatch"))
+            }
+            position += 4;
+
+            // Each channel is stored separately, as runs and literal spans.
+            for channel in 0..4 {
+                let mut x = 0;
+
+                while x < width {
+                    let count = *bytes.get(position).ok_or_else(truncated)? as usize;
+                    position += 1;
+
+                    if count > 128 {
+                        let count = count - 128;
+                        let value = *bytes.get(position).ok_or_else(truncated)?;
+                        position += 1;
+
+                        if count > width - x {
+                            return Err(invalid_data("HDR run overflows scanline"))
+                        }
+                        scanline[x..x + count].iter_mut().for_each(|pixel| pixel[channel] = value);
+                        x += count;
+                    } else {
+                        if count == 0 || count > width - x {
+                            return Err(invalid_data("invalid HDR run length"))
+                        }
+                        let values = bytes.get(position..position + count).ok_or_else(truncated)?;
+                        position += count;
+
+                        scanline[x..x + count].iter_mut().zip(values).for_each(|(pixel, &value)| pixel[channel] = value);
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            let values = bytes.get(position..position + width * 4).ok_or_else(truncated)?;
+            position += width * 4;
+
+            for (pixel, value) in scanline.iter_mut().zip(values.chunks_exact(4)) {
+                pixel.copy_from_slice(value);
+            }
+        }
+
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                return Vec3D::zero()
+            }
+
+            let scale = 2.0f32.powi(e as i32 - (128 + 8));
+            Vec3D::new(r as f32 + 0.5, g as f32 + 0.5, b as f32 + 0.5) * scale
+        }));
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Reads color ('PF') or grayscale ('Pf') portable float maps, stored bottom up.
+fn decode_pfm(bytes: &[u8]) -> io::Result<(usize, usize, Vec<Vec3D>)> {
+    let mut position = 0;
+
+    let channels = match next_line(bytes, &mut position)? {
+        b"PF" => 3,
+        b"Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file")),
+    };
+
+    let mut header = String::new();
+    while header.split_whitespace().count() < 3 {
+        header.push(' ');
+        header.push_str(&String::from_utf8_lossy(next_line(bytes, &mut position)?));
+    }
+
+    let fields: Vec<&str> = header.split_whitespace().collect();
+    let invalid = |field: &str| invalid_data(&format!("invalid PFM header value '{}'", field));
+
+    let size = |field: &str| field.parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(|| invalid(field));
+    let (width, height) = (size(fields[0])?, size(fields[1])?);
+    let scale = fields[2].parse::<f32>().ok().filter(|scale| scale.is_finite() && *scale != 0.0).ok_or_else(|| invalid(fields[2]))?;
+
+    let length = pixel_count(width, height)?.checked_mul(channels * 4).ok_or_else(too_large)?;
+    let data = position.checked_add(length).and_then(|end| bytes.get(position..end))
+        .ok_or_else(|| invalid_data("unexpected end of PFM data"))?;
+
+    // A negative scale marks little endian data.
+    let values: Vec<f32> = data.chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if scale < 0.0 { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+        })
+        .collect();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in values.chunks_exact(width * channels).rev() {
+        pixels.extend(row.chunks_exact(channels).map(|value| match value {
+            [gray] => Vec3D::one() * *gray,
+            [r, g, b] => Vec3D::new(*r, *g, *b),
+            _ => unreachable!(),
+        }));
+    }
+
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pfm(header: &str, values: &[f32]) -> Vec<u8> {
+        header.bytes().chain(values.iter().flat_map(|value| value.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn decodes_pfm_bottom_up() {
+        let (width, height, pixels) = decode_pfm(&pfm("Pf\n1 2\n-1\n", &[1.0, 2.0])).unwrap();
+
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(pixels, vec![Vec3D::one() * 2.0, Vec3D::one()]);
+    }
+
+    #[test]
+    fn decodes_color_pfm() {
+        let (_, _, pixels) = decode_pfm(&pfm("PF\n1 1\n-1.0\n", &[0.25, 0.5, 0.75])).unwrap();
+
+        assert_eq!(pixels, vec![Vec3D::new(0.25, 0.5, 0.75)]);
+    }
+
+    #[test]
+    fn rejects_malformed_pfm_headers() {
+        for header in ["P6\n1 1\n-1\n", "Pf\n0 1\n-1\n", "Pf\n1 0\n-1\n", "Pf\n-1 1\n-1\n", "Pf\nNaN 1\n-1\n", "Pf\ninf 1\n-1\n", "Pf\n1 1\n0\n", "Pf\n1 1\nNaN\n"] {
+            let error = decode_pfm(&pfm(header, &[1.0])).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn rejects_pfm_sizes_overflowing_the_length() {
+        let header = format!("PF\n{} {}\n-1\n", usize::MAX / 2, 3);
+
+        assert!(decode_pfm(&pfm(&header, &[1.0])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_pfm_data() {
+        assert!(decode_pfm(&pfm("PF\n2 2\n-1\n", &[1.0; 11])).is_err());
+    }
+
+    const HDR_HEADER: &str = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+
+    #[test]
+    fn decodes_flat_hdr() {
+        let mut bytes = format!("{}-Y 1 +X 2\n", HDR_HEADER).into_bytes();
+        bytes.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let (width, height, pixels) = decode_hdr(&bytes).unwrap();
+
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels[0], Vec3D::new(128.5, 64.5, 0.5) * 2.0f32.powi(-7));
+        assert_eq!(pixels[1], Vec3D::zero());
+    }
+
+    #[test]
+    fn decodes_run_length_encoded_hdr() {
+        let mut bytes = format!("{}-Y 1 +X 8\n", HDR_HEADER).into_bytes();
+        bytes.extend([2, 2, 0, 8]);
+        // Red as one run, green and blue as literals, the exponent as one run.
+        bytes.extend([128 + 8, 255]);
+        bytes.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        bytes.extend([8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend([128 + 8, 136]);
+
+        let (_, _, pixels) = decode_hdr(&bytes).unwrap();
+
+        assert_eq!(pixels.len(), 8);
+        assert_eq!(pixels[3], Vec3D::new(255.5, 3.5, 0.5));
+    }
+
+    #[test]
+    fn rejects_malformed_hdr_headers() {
+        for header in ["P6\n\n-Y 1 +X 1\n", "#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n", "#?RADIANCE\n\n+Y 1 +X 1\n", "#?RADIANCE\n\n-Y -1 +X 1\n", "#?RADIANCE\n\n-Y 99999999999 +X 0\n", "#?RADIANCE\n\n-Y 0 +X 1\n"] {
+            let mut bytes = header.as_bytes().to_vec();
+            bytes.extend([0; 4]);
+
+            assert!(decode_hdr(&bytes).is_err(), "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn rejects_hdr_sizes_beyond_the_data() {
+        let bytes = format!("{}-Y {} +X {}\n", HDR_HEADER, usize::MAX, usize::MAX).into_bytes();
+        assert!(decode_hdr(&bytes).is_err());
+
+        let bytes = format!("{}-Y 1 +X 100000000000\n", HDR_HEADER).into_bytes();
+        assert!(decode_hdr(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_hdr_runs() {
+        let mut bytes = format!("{}-Y 1 +X 8\n", HDR_HEADER).into_bytes();
+        bytes.extend([2, 2, 0, 8, 128 + 9, 0]);
+
+        assert!(decode_hdr(&bytes).is_err());
+    }
+}
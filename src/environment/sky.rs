@@ -0,0 +1,207 @@
+use std::f32::consts::PI;
+
+use crate::{random, degrees_to_radians};
+
+use super::*;
+
+/// Renderer radiance per candela per square meter, putting a sunlit white
+/// surface close to 1.
+const LUMINANCE_SCALE: f32 = 2.5e-5;
+
+/// Illuminance of sunlight above the atmosphere, in lux.
+const SOLAR_ILLUMINANCE: f32 = 128_000.0;
+
+/// Apparent diameter of the sun, in degrees.
+const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+/// Share of 'Sky::sample' calls spent on the sun rather than the whole sky.
+const SUN_SAMPLE_PROBABILITY: f32 = 0.5;
+
+/// Wavelengths in micrometers standing in for the red, green and blue channels.
+const WAVELENGTHS: [f32; 3] = [0.680, 0.550, 0.440];
+
+/// Perez sky distribution coefficients A to E.
+type Perez = [f32; 5];
+
+/// Preetham, Shirley and Smits' analytic daylight model: clear sky radiance
+/// from the sun position and the haziness of the air, plus the sun itself.
+pub struct Sky {
+    /// Unit vector towards the sun.
+    sun_direction: Vec3D,
+    turbidity: f32,
+    /// Multiplies sky and sun radiance.
+    pub intensity: f32,
+
+    /// Zenith luminance in renderer units and chromaticity.
+    zenith: [f32; 3],
+    perez: [Perez; 3],
+    /// 'perez' evaluated at the zenith, which every direction is relative to.
+    zenith_perez: [f32; 3],
+
+    sun_radiance: Vec3D,
+    sun_cos_theta_max: f32,
+    sun_solid_angle: f32,
+}
+
+impl Sky {
+    /// 'sun_direction' points towards the sun and need not be normalized.
+    /// 'turbidity' ranges from 2 for very clear air to 10 for haze.
+    pub fn new(sun_direction: Vec3D, turbidity: f32) -> Self {
+        let sun_direction = sun_direction.unit();
+        let turbidity = turbidity.clamp(1.0, 20.0);
+        let t = turbidity;
+
+        // The model only covers a sun above the horizon.
+        let theta_s = sun_direction.y.clamp(0.0, 1.0).acos().min(PI / 2.0 - 0.001);
+        let theta_s_powers = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let polynomial = |coefficients: [[f32; 4]; 3]| -> f32 {
+            let [t2, t1, t0] = coefficients.map(|row| row.iter().zip(theta_s_powers).map(|(c, p)| c * p).sum::<f32>());
+            t.powi(2) * t2 + t * t1 + t0
+        };
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let zenith_x = polynomial([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = polynomial([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+        let zenith_perez = perez.map(|coefficients| perez_function(&coefficients, 0.0, theta_s));
+
+        // '1 - cos' written as '2 sin²(θ/2)', which does not cancel for a disc this small.
+        let sun_half_angle = degrees_to_radians(SUN_ANGULAR_DIAMETER / 2.0);
+        let sun_cos_theta_max = sun_half_angle.cos();
+        let sun_solid_angle = 4.0 * PI * (sun_half_angle / 2.0).sin().powi(2);
+
+        let sun_radiance = if sun_direction.y > 0.0 {
+            let [r, g, b] = sun_transmittance(theta_s, turbidity);
+            Vec3D::new(r, g, b) * SOLAR_ILLUMINANCE * LUMINANCE_SCALE / sun_solid_angle
+        } else {
+            Vec3D::zero()
+        };
+
+        Self {
+            sun_direction,
+            turbidity,
+            intensity: 1.0,
+            // kcd/m² to cd/m².
+            zenith: [zenith_luminance * 1000.0 * LUMINANCE_SCALE, zenith_x, zenith_y],
+            perez,
+            zenith_perez,
+            sun_radiance,
+            sun_cos_theta_max,
+            sun_solid_angle,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn sun_direction(&self) -> Vec3D {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> f32 {
+        self.turbidity
+    }
+
+    /// Radiance of the sky alone along the unit vector 'direction'. Below the
+    /// horizon the sky keeps its horizon color.
+    fn sky_radiance(&self, direction: &Vec3D) -> Vec3D {
+        let cos_theta = direction.y.max(0.001);
+        let theta = cos_theta.acos();
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y] = [0, 1, 2]
+            .map(|i| self.zenith[i] * perez_function(&self.perez[i], theta, gamma) / self.zenith_perez[i]);
+
+        if y <= 0.0 || luminance <= 0.0 {
+            return Vec3D::zero()
+        }
+
+        // xyY to XYZ to linear sRGB, clipping colors outside the sRGB gamut.
+        let (cie_x, cie_y, cie_z) = (x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+
+        Vec3D::new(
+            (3.2406 * cie_x - 1.5372 * cie_y - 0.4986 * cie_z).max(0.0),
+            (-0.9689 * cie_x + 1.8758 * cie_y + 0.0415 * cie_z).max(0.0),
+            (0.0557 * cie_x - 0.2040 * cie_y + 1.0570 * cie_z).max(0.0),
+        )
+    }
+
+    fn sun_probability(&self) -> f32 {
+        if self.sun_radiance == Vec3D::zero() { 0.0 } else { SUN_SAMPLE_PROBABILITY }
+    }
+
+    fn in_sun(&self, direction: &Vec3D) -> bool {
+        direction.dot(&self.sun_direction) >= self.sun_cos_theta_max
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: &Vec3D) -> Vec3D {
+        let direction = direction.unit();
+        let mut radiance = self.sky_radiance(&direction);
+
+        if self.in_sun(&direction) {
+            radiance += self.sun_radiance;
+        }
+
+        self.intensity * radiance
+    }
+
+    /// Picks the sun disc or any direction of the sky equally often.
+    fn sample(&self) -> Option<EnvironmentSample> {
+        let direction = if random() < self.sun_probability() {
+            Vec3D::random_in_cone(&self.sun_direction, self.sun_cos_theta_max)
+        } else {
+            Vec3D::random_unit()
+        };
+
+        Some(EnvironmentSample { direction, radiance: self.radiance(&direction), pdf: self.pdf(&direction) })
+    }
+
+    fn pdf(&self, direction: &Vec3D) -> f32 {
+        let sun_probability = self.sun_probability();
+        let sun_pdf = if self.in_sun(&direction.unit()) {
+            1.0 / self.sun_solid_angle
+        } else {
+            0.0
+        };
+
+        sun_probability * sun_pdf + (1.0 - sun_probability) / (4.0 * PI)
+    }
+}
+
+/// Relative radiance of the sky at zenith angle 'theta' and angle 'gamma' from the sun.
+fn perez_function(&[a, b, c, d, e]: &Perez, theta: f32, gamma: f32) -> f32 {
+    (1.0 + a * (b / theta.cos().max(0.001)).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// Fraction of sunlight reaching the ground through Rayleigh and aerosol
+/// scattering, for the sun at zenith angle 'theta_s'.
+fn sun_transmittance(theta_s: f32, turbidity: f32) -> [f32; 3] {
+    // Relative optical mass of the air the light passes through.
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608365 * turbidity - 0.04586025;
+
+    WAVELENGTHS.map(|lambda| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+
+        rayleigh * aerosol
+    })
+}
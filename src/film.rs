@@ -0,0 +1,68 @@
+use crate::vector::Vec3D;
+
+/// High dynamic range accumulation buffer holding the sum of linear radiance
+/// samples and the number of samples for every pixel.
+#[derive(Clone, Debug)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    sums: Vec<Vec3D>,
+    counts: Vec<u32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Vec3D::zero(); width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add_sample(&mut self, x: usize, y: usize, radiance: Vec3D) {
+        self.add_samples(x, y, radiance, 1);
+    }
+
+    /// Adds 'count' samples whose radiance sums to 'sum'.
+    pub fn add_samples(&mut self, x: usize, y: usize, sum: Vec3D, count: u32) {
+        let index = y * self.width + x;
+
+        self.sums[index] += sum;
+        self.counts[index] += count;
+    }
+
+    /// Average radiance of the pixel, black if it has no samples yet.
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3D {
+        self.average(y * self.width + x)
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// Average radiance of every pixel, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = Vec3D> + '_ {
+        (0..self.sums.len()).map(|index| self.average(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.sums.fill(Vec3D::zero());
+        self.counts.fill(0);
+    }
+
+    fn average(&self, index: usize) -> Vec3D {
+        match self.counts[index] {
+            0 => Vec3D::zero(),
+            count => self.sums[index] / count as f32,
+        }
+    }
+}
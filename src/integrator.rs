@@ -0,0 +1,329 @@
+use crate::{vector::Vec3D, ray::Ray, body::{Body, HitRecord}, interval::Interval, world::World, random};
+
+/// Unidirectional path tracer with next-event estimation and multiple
+/// importance sampling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Integrator {
+    /// Bounces every path survives before Russian roulette may end it.
+    pub min_bounces: usize,
+    /// Bounces after which a path is cut off regardless of its throughput.
+    pub max_bounces: usize,
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Self { min_bounces: 3, max_bounces: 50 }
+    }
+}
+
+impl Integrator {
+    /// Light arriving at the origin of 'ray' from its direction.
+    pub fn radiance(&self, ray: &Ray, world: &World) -> Vec3D {
+        let mut ray = *ray;
+        let mut radiance = Vec3D::zero();
+        let mut throughput = Vec3D::one();
+        // Density the previous hit chose 'ray' with, if it also sampled the lights.
+        let mut scattering_pdf: Option<f32> = None;
+
+        for bounce in 0..=self.max_bounces {
+            let mut hit_record = HitRecord::new();
+
+            if !world.bodies.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut hit_record) {
+                let mut background_color = world.environment.radiance(&ray.direction);
+
+                if let Some(scattering_pdf) = scattering_pdf {
+                    background_color *= power_heuristic(scattering_pdf, world.environment.pdf(&ray.direction));
+                }
+
+                radiance += throughput * background_color;
+                break
+            }
+
+            let mut emitted_color = hit_record.material.emit(&hit_record);
+
+            // Light found by scattering is weighted against the light sample taken at the previous
+            // hit, if it came from one of the lights sampled there.
+            if let Some(scattering_pdf) = scattering_pdf {
+                if hit_record.material.is_emissive() && Self::is_sampled_light(&ray, world, &hit_record) {
+                    let light_pdf = world.lights.pdf_value(&ray.origin, &ray.direction);
+                    emitted_color *= power_heuristic(scattering_pdf, light_pdf);
+                }
+            }
+
+            radiance += throughput * emitted_color;
+
+            let Some(sample) = hit_record.material.sample(&ray, &hit_record) else {
+                break
+            };
+
+            // Delta lobes scatter into discrete directions, so they can only find
+            // lights by chance.
+            let lights_sampled = !sample.is_delta && sample.pdf > 0.0;
+
+            if lights_sampled {
+                radiance += throughput * Self::sample_light_sources(&ray, world, &hit_record);
+                radiance += throughput * Self::sample_environment(&ray, world, &hit_record);
+
+                if !world.lights.is_empty() {
+                    radiance += throughput * Self::sample_lights(&ray, world, &hit_record);
+                }
+            }
+
+            scattering_pdf = Some(sample.pdf).filter(|_| lights_sampled);
+
+            throughput *= sample.weight;
+
+            if bounce >= self.min_bounces {
+                // Paths carrying little light are ended early, and the survivors
+                // scaled up so the estimate stays unbiased.
+                let survival = throughput.max_component().min(1.0);
+                if random() >= survival {
+                    break
+                }
+
+                throughput /= survival;
+            }
+
+            ray = Ray::new(&hit_record.point, &sample.direction).with_time(ray.time);
+        }
+
+        radiance
+    }
+
+    /// Next-event estimation: light reaching 'hit_record' straight from a
+    /// random point on one of 'world.lights', dimmed by whatever lies in
+    /// between and weighted against finding the same light by scattering.
+    fn sample_lights(ray_in: &Ray, world: &World, hit_record: &HitRecord) -> Vec3D {
+        let direction = world.lights.random_direction(&hit_record.point);
+        let light_pdf = world.lights.pdf_value(&hit_record.point, &direction);
+        if light_pdf <= 0.0 {
+            return Vec3D::zero()
+        }
+
+        let bsdf = hit_record.material.eval(ray_in, hit_record, &direction);
+        if bsdf == Vec3D::zero() {
+            return Vec3D::zero()
+        }
+
+        let shadow_ray = Ray::new(&hit_record.point, &direction).with_time(ray_in.time);
+        let scattering_pdf = hit_record.material.pdf(ray_in, hit_record, &direction);
+
+        // Only the sampled lights count, so emission of anything else along the way,
+        // like glowing volumes, is left to scattering.
+        let mut light_record = HitRecord::new();
+        if !world.lights.hit(&shadow_ray, Interval::new(0.001, f32::INFINITY), &mut light_record) {
+            return Vec3D::zero()
+        }
+
+        // Stops short of the light, which would otherwise block itself.
+        let transmittance = world.bodies.transmittance(&shadow_ray, Interval::new(0.001, light_record.t * (1.0 - 1e-4)));
+        if transmittance <= 0.0 {
+            return Vec3D::zero()
+        }
+
+        let weight = power_heuristic(light_pdf, scattering_pdf);
+
+        weight * transmittance * bsdf * light_record.material.emit(&light_record) / light_pdf
+    }
+
+    /// Whether 'hit_record' lies on one of 'world.lights'. Other emissive
+    /// bodies are never sampled, so only scattering finds their light, even
+    /// where a light behind them makes 'pdf_value' non-zero.
+    fn is_sampled_light(ray: &Ray, world: &World, hit_record: &HitRecord) -> bool {
+        let mut light_record = HitRecord::new();
+
+        world.lights.hit(ray, Interval::new(0.001, hit_record.t * (1.0 + 1e-4)), &mut light_record)
+            && light_record.t >= hit_record.t * (1.0 - 1e-4)
+    }
+
+    /// Light reaching 'hit_record' from a direction chosen by the environment,
+    /// weighted against finding it by scattering.
+    fn sample_environment(ray_in: &Ray, world: &World, hit_record: &HitRecord) -> Vec3D {
+        let Some(sample) = world.environment.sample() else {
+            return Vec3D::zero()
+        };
+
+        let bsdf = hit_record.material.eval(ray_in, hit_record, &sample.direction);
+        if bsdf == Vec3D::zero() {
+            return Vec3D::zero()
+        }
+
+        let shadow_ray = Ray::new(&hit_record.point, &sample.direction).with_time(ray_in.time);
+        let transmittance = world.bodies.transmittance(&shadow_ray, Interval::new(0.001, f32::INFINITY));
+        if transmittance <= 0.0 {
+            return Vec3D::zero()
+        }
+
+        let weight = power_heuristic(sample.pdf, hit_record.material.pdf(ray_in, hit_record, &sample.direction));
+
+        weight * transmittance * bsdf * sample.radiance / sample.pdf
+    }
+
+    /// Light reaching 'hit_record' from every light source, dimmed by whatever
+    /// lies in between.
+    fn sample_light_sources(ray_in: &Ray, world: &World, hit_record: &HitRecord) -> Vec3D {
+        let mut color = Vec3D::zero();
+
+        for light_source in &world.light_sources {
+            let Some(sample) = light_source.sample(&hit_record.point) else {
+                continue
+            };
+
+            let bsdf = hit_record.material.eval(ray_in, hit_record, &sample.direction);
+            if bsdf == Vec3D::zero() {
+                continue
+            }
+
+            let shadow_ray = Ray::new(&hit_record.point, &sample.direction).with_time(ray_in.time);
+            let transmittance = world.bodies.transmittance(&shadow_ray, Interval::new(0.001, sample.distance));
+
+            color += transmittance * bsdf * sample.intensity;
+        }
+
+        color
+    }
+}
+
+/// Multiple importance sampling weight of a sample drawn with density 'pdf',
+/// against another strategy that would have drawn it with 'other_pdf'.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    // Lights covering almost no solid angle overflow the square, which would divide infinity by infinity.
+    if pdf.is_infinite() {
+        return 1.0
+    }
+
+    let (pdf2, other_pdf2) = (pdf.powi(2), other_pdf.powi(2));
+
+    if pdf2 + other_pdf2 > 0.0 { pdf2 / (pdf2 + other_pdf2) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, f32};
+
+    use super::*;
+    use crate::{
+        body::{body_list::BodyList, bodies::{Sphere, Plane, Quad, Cylinder}},
+        material::{Material, materials::{Light, Lambertian}},
+        environment::{Environment, environments::{UniformEnvironment, EnvironmentMap}},
+    };
+
+    /// Average radiance along 'ray' over 'samples' paths.
+    fn estimate(integrator: Integrator, world: &World, ray: &Ray, samples: usize) -> Vec3D {
+        (0..samples).fold(Vec3D::zero(), |sum, _| sum + integrator.radiance(ray, world)) / samples as f32
+    }
+
+    fn assert_near(color: Vec3D, expected: f32, tolerance: f32) {
+        for component in [color.x, color.y, color.z] {
+            assert!((component - expected).abs() < tolerance, "{:?} is not {}", color, expected);
+        }
+    }
+
+    /// Diffuse sphere of 'albedo' around the origin, lit by 'environment' alone.
+    fn furnace(albedo: f32, environment: Arc<dyn Environment>) -> World {
+        let mut bodies = BodyList::new();
+        bodies.push(Arc::new(Sphere { center: Vec3D::zero(), radius: 1.0, material: Arc::new(Lambertian::new(Vec3D::one() * albedo)) }));
+
+        World::new(Arc::new(bodies)).with_environment(environment)
+    }
+
+    fn white() -> Arc<dyn Environment> {
+        Arc::new(UniformEnvironment::new(Vec3D::one()))
+    }
+
+    fn towards_sphere() -> Ray {
+        Ray::new(&Vec3D::new(0.3, 0.2, -5.0), &Vec3D::z_unit())
+    }
+
+    #[test]
+    fn furnace_reflects_the_albedo() {
+        // Light leaving a convex body escapes straight away, so every path carries exactly the albedo.
+        let integrator = Integrator { min_bounces: 5, max_bounces: 5 };
+        assert_near(estimate(integrator, &furnace(0.5, white()), &towards_sphere(), 100), 0.5, 1e-5);
+    }
+
+    #[test]
+    fn russian_roulette_stays_unbiased() {
+        let integrator = Integrator { min_bounces: 0, max_bounces: 50 };
+        assert_near(estimate(integrator, &furnace(0.5, white()), &towards_sphere(), 20000), 0.5, 0.02);
+    }
+
+    #[test]
+    fn sampled_environments_match_the_furnace() {
+        let map = Arc::new(EnvironmentMap::new(8, 4, vec![Vec3D::one(); 32]));
+
+        for integrator in [Integrator { min_bounces: 5, max_bounces: 5 }, Integrator { min_bounces: 0, max_bounces: 50 }] {
+            assert_near(estimate(integrator, &furnace(0.5, map.clone()), &towards_sphere(), 20000), 0.5, 0.02);
+        }
+    }
+
+    #[test]
+    fn white_furnace_conserves_energy_between_bodies() {
+        // Light bounces between the sphere and the floor it rests on, but with
+        // nothing absorbed, every path that escapes carries all of it.
+        let white_material: Arc<dyn Material> = Arc::new(Lambertian::new(Vec3D::one()));
+        let mut bodies = BodyList::new();
+        bodies.push(Arc::new(Sphere { center: Vec3D::zero(), radius: 1.0, material: white_material.clone() }));
+        bodies.push(Arc::new(Plane { center: Vec3D::new(0.0, -1.0, 0.0), normal: Vec3D::y_unit(), material: white_material }));
+        let world = World::new(Arc::new(bodies)).with_environment(white());
+
+        let ray = Ray::new(&Vec3D::new(0.0, -0.8, -5.0), &Vec3D::z_unit());
+
+        for integrator in [Integrator { min_bounces: 50, max_bounces: 50 }, Integrator { min_bounces: 0, max_bounces: 50 }] {
+            assert_near(estimate(integrator, &world, &ray, 5000), 1.0, 0.01);
+        }
+    }
+
+    #[test]
+    fn light_sampling_agrees_with_scattering() {
+        // Unit square light facing down 1 above a diffuse floor, seen where it lies right over the floor.
+        let mut bodies = BodyList::new();
+        bodies.push(Arc::new(Quad { corner: Vec3D::new(-0.5, 1.0, -0.5), u: Vec3D::x_unit(), v: Vec3D::z_unit(), material: Arc::new(Light::new(Vec3D::one() * 4.0)) }));
+        bodies.push(Arc::new(Plane { center: Vec3D::zero(), normal: Vec3D::y_unit(), material: Arc::new(Lambertian::new(Vec3D::one() * 0.5)) }));
+
+        let world = World::new(Arc::new(bodies)).with_environment(Arc::new(UniformEnvironment::new(Vec3D::zero())));
+        let ray = Ray::new(&Vec3D::new(0.0, 0.5, -2.0), &Vec3D::new(0.0, -0.5, 2.0));
+
+        // Albedo times emission times the form factor of a square seen from below its center.
+        let corner = 0.5 / 1.25_f32.sqrt() * (0.5 / 1.25_f32.sqrt()).atan() / f32::consts::PI;
+        let expected = 0.5 * 4.0 * 4.0 * corner;
+
+        let with_light_sampling = estimate(Integrator::default(), &world, &ray, 20000);
+        assert_near(with_light_sampling, expected, 0.01);
+
+        // Without lights to sample, only scattering finds the light.
+        let world = World { lights: BodyList::new(), ..world };
+        let scattering_only = estimate(Integrator::default(), &world, &ray, 20000);
+        assert_near(scattering_only, expected, 0.03);
+    }
+
+    #[test]
+    fn power_heuristic_weights_sum_to_one() {
+        for (pdf, other_pdf) in [(1.0, 1.0), (0.1, 5.0), (3.0, 0.0), (1e-6, 1e6), (f32::INFINITY, 2.0)] {
+            let sum = power_heuristic(pdf, other_pdf) + power_heuristic(other_pdf, pdf);
+            assert!((sum - 1.0).abs() < 1e-6, "{} and {} sum to {}", pdf, other_pdf, sum);
+        }
+    }
+
+    #[test]
+    fn only_collected_lights_are_sampled_lights() {
+        let light: Arc<dyn Material> = Arc::new(Light::new(Vec3D::one()));
+        let mut bodies = BodyList::new();
+        bodies.push(Arc::new(Quad { corner: Vec3D::new(-2.0, 5.0, -2.0), u: Vec3D::new(0.0, 0.0, 4.0), v: Vec3D::new(4.0, 0.0, 0.0), material: light.clone() }));
+        bodies.push(Arc::new(Cylinder { center: Vec3D::new(0.0, 2.0, 0.0), radius: 0.5, height: 1.0, material: light }));
+        let world = World::new(Arc::new(bodies));
+
+        // Cylinders glow, but are not sampled as lights, though the quad behind it is.
+        assert_eq!(world.lights.len(), 1);
+
+        let hit = |origin: Vec3D| {
+            let ray = Ray::new(&origin, &Vec3D::y_unit());
+            let mut hit_record = HitRecord::new();
+            assert!(world.bodies.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut hit_record));
+            Integrator::is_sampled_light(&ray, &world, &hit_record)
+        };
+
+        assert!(!hit(Vec3D::zero()));
+        assert!(hit(Vec3D::new(1.0, 0.0, 1.0)));
+    }
+}
@@ -1,6 +1,7 @@
 pub const EMPTY: Interval = Interval::new(f32::INFINITY, f32::NEG_INFINITY);
 pub const UNIVERSE: Interval = Interval::new(f32::NEG_INFINITY, f32::INFINITY);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Interval {
     pub min: f32,
     pub max: f32,
@@ -11,6 +12,20 @@ impl Interval {
         Self { min, max }
     }
 
+    /// Smallest interval containing both 'a' and 'b'.
+    pub fn enclosing(a: &Interval, b: &Interval) -> Self {
+        Self { min: a.min.min(b.min), max: a.max.max(b.max) }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.max - self.min
+    }
+
+    pub fn expand(&self, delta: f32) -> Self {
+        let padding = delta / 2.0;
+        Self { min: self.min - padding, max: self.max + padding }
+    }
+
     pub fn contains(&self, x: f32) -> bool {
         x >= self.min && x <= self.max
     }
@@ -28,4 +43,4 @@ impl Interval {
             x
         }
     }
-}
\ No newline at end of file
+}
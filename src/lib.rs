@@ -0,0 +1,48 @@
+pub mod vector;
+pub mod ray;
+pub mod body;
+pub mod interval;
+pub mod aabb;
+pub mod transform;
+pub mod camera;
+pub mod environment;
+pub mod film;
+pub mod integrator;
+pub mod material;
+pub mod light;
+pub mod texture;
+pub mod obj;
+pub mod output;
+pub mod post_process;
+pub mod scene;
+pub mod sdf;
+pub mod world;
+
+use std::{cell::Cell, collections::hash_map::RandomState, hash::{BuildHasher, Hasher}};
+
+thread_local! {
+    // Each render thread gets its own generator so sampling never contends on shared state.
+    static RNG_STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+pub fn degrees_to_radians(degrees: f32) -> f32 {
+    degrees * std::f32::consts::PI / 180.0
+}
+
+/// Uniform in [0, 1).
+pub fn random() -> f32 {
+    RNG_STATE.with(|state| {
+        // xorshift64*
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+
+        (x.wrapping_mul(0x2545F4914F6CDD1D) >> 40) as f32 / (1u64 << 24) as f32
+    })
+}
+
+pub fn random_neg_pos() -> f32 {
+    2.0 * random() - 1.0
+}
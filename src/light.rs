@@ -0,0 +1,23 @@
+use crate::vector::Vec3D;
+
+pub mod lights;
+
+/// Light arriving at a point from a 'LightSource'.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the lit point towards the light.
+    pub direction: Vec3D,
+    /// Distance to the light along 'direction'. Infinite for lights at infinity.
+    pub distance: f32,
+    /// Radiance arriving along 'direction' divided by the density of choosing it,
+    /// or the irradiance on a surface facing the light for delta lights.
+    pub intensity: Vec3D,
+}
+
+/// Light that is not a body, so rays never hit it. Only found by the
+/// integrator sampling it directly with shadow rays.
+pub trait LightSource: Send + Sync {
+    /// Picks a direction from 'point' towards the light, or 'None' if no light
+    /// reaches 'point'.
+    fn sample(&self, point: &Vec3D) -> Option<LightSample>;
+}
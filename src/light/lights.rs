@@ -0,0 +1,111 @@
+use crate::degrees_to_radians;
+
+use super::*;
+
+/// Emits 'intensity' per unit solid angle equally in every direction from 'position'.
+pub struct PointLight {
+    pub position: Vec3D,
+    pub intensity: Vec3D,
+}
+
+impl LightSource for PointLight {
+    fn sample(&self, point: &Vec3D) -> Option<LightSample> {
+        let offset = self.position - *point;
+        let distance = offset.mag();
+
+        if distance <= 0.0 {
+            return None
+        }
+
+        Some(LightSample { direction: offset / distance, distance, intensity: self.intensity / distance.powi(2) })
+    }
+}
+
+/// Point light restricted to a cone around 'direction'. Full 'intensity'
+/// within 'inner_angle' of the axis, fading smoothly to nothing at 'outer_angle'.
+pub struct SpotLight {
+    pub position: Vec3D,
+    pub direction: Vec3D,
+    pub intensity: Vec3D,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl SpotLight {
+    /// Angles are in degrees from the axis. 'direction' need not be normalized.
+    pub fn new(position: Vec3D, direction: Vec3D, intensity: Vec3D, inner_angle: f32, outer_angle: f32) -> Self {
+        let outer_angle = outer_angle.max(inner_angle);
+
+        Self {
+            position,
+            direction: direction.unit(),
+            intensity,
+            cos_inner: degrees_to_radians(inner_angle).cos(),
+            cos_outer: degrees_to_radians(outer_angle).cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f32) -> f32 {
+        if cos_theta >= self.cos_inner {
+            return 1.0
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.0
+        }
+
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl LightSource for SpotLight {
+    fn sample(&self, point: &Vec3D) -> Option<LightSample> {
+        let offset = self.position - *point;
+        let distance = offset.mag();
+
+        if distance <= 0.0 {
+            return None
+        }
+
+        let direction = offset / distance;
+        let falloff = self.falloff(-direction.dot(&self.direction));
+
+        if falloff <= 0.0 {
+            return None
+        }
+
+        Some(LightSample { direction, distance, intensity: falloff * self.intensity / distance.powi(2) })
+    }
+}
+
+/// Light from infinitely far away travelling along 'direction', such as the
+/// sun. 'irradiance' is measured on a surface facing the light. A non-zero
+/// 'angular_diameter' spreads it over a disc of the sky, softening shadows.
+pub struct DirectionalLight {
+    pub direction: Vec3D,
+    pub irradiance: Vec3D,
+    cos_theta_max: f32,
+}
+
+impl DirectionalLight {
+    /// 'angular_diameter' is in degrees. 'direction' need not be normalized.
+    pub fn new(direction: Vec3D, irradiance: Vec3D, angular_diameter: f32) -> Self {
+        Self {
+            direction: direction.unit(),
+            irradiance,
+            cos_theta_max: degrees_to_radians(angular_diameter / 2.0).cos(),
+        }
+    }
+}
+
+impl LightSource for DirectionalLight {
+    fn sample(&self, _point: &Vec3D) -> Option<LightSample> {
+        let direction = if self.cos_theta_max < 1.0 {
+            Vec3D::random_in_cone(&-self.direction, self.cos_theta_max)
+        } else {
+            -self.direction
+        };
+
+        Some(LightSample { direction, distance: f32::INFINITY, intensity: self.irradiance })
+    }
+}
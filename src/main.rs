@@ -1,65 +1,161 @@
-mod vector;
-mod ray;
-mod body;
-mod interval;
-mod camera;
-mod material;
-
-use std::{rc::Rc, cell::RefCell, ops::Neg};
-use body::{body_list::BodyList, bodies::{Sphere, Plane}, Body};
-use camera::Camera;
-use macroquad::{prelude::*, miniquad::window::set_window_size, rand::{RandomRange}};
-use material::materials::{Base, Lambertian, Metal, Dielectric, Light};
-use vector::Vec3D;
-
-pub fn degrees_to_radians(degrees: f32) -> f32 {
-    degrees * std::f32::consts::PI / 180.0
+use std::{env, process, sync::Arc, time::Instant};
+use raytracing::{
+    body::{Body, body_list::BodyList, bodies::{Sphere, Plane}, bvh::{BvhNode, SplitMethod}},
+    camera::Camera,
+    light::lights::DirectionalLight,
+    material::materials::Lambertian,
+    output::write_image,
+    scene::{self, Scene, SceneError},
+    vector::Vec3D,
+    world::World,
+};
+use macroquad::{prelude::*, miniquad::window::set_window_size};
+
+const USAGE: &str = "\
+usage: raytracing [--scene PATH] [--headless] [--samples N] [--output PATH]...
+
+  --scene PATH   TOML scene file to render instead of the built-in scene
+  --headless     render without opening a window and write the result to disk
+  --samples N    samples per pixel to render in headless mode (default from
+                 the scene)
+  --output PATH  image to write in headless mode, .png or .ppm; may be repeated
+                 (default render.png and render.ppm)";
+
+struct Options {
+    scene: Option<String>,
+    headless: bool,
+    samples: Option<usize>,
+    outputs: Vec<String>,
 }
 
-pub fn random() -> f32 {
-    f32::gen_range(0.0, 1.0)
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self { scene: None, headless: false, samples: None, outputs: vec![] };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scene" => options.scene = Some(args.next().ok_or("--scene needs a path")?),
+                "--headless" => options.headless = true,
+                "--samples" => {
+                    let value = args.next().ok_or("--samples needs a value")?;
+                    options.samples = Some(value.parse()
+                        .ok()
+                        .filter(|&samples| samples > 0)
+                        .ok_or_else(|| format!("invalid sample count '{}'", value))?);
+                },
+                "--output" => options.outputs.push(args.next().ok_or("--output needs a path")?),
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                },
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        if options.outputs.is_empty() {
+            options.outputs = vec!["render.png".to_string(), "render.ppm".to_string()];
+        }
+
+        Ok(options)
+    }
 }
 
-pub fn random_neg_pos() -> f32 {
-    f32::gen_range(-1.0, 1.0)
+fn main() {
+    let options = Options::parse(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        process::exit(2);
+    });
+
+    let (camera, world) = match &options.scene {
+        Some(path) => load_scene(path).unwrap_or_else(|error| {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }),
+        None => default_scene(),
+    };
+
+    if options.headless {
+        render_headless(&options, camera, &world);
+    } else {
+        macroquad::Window::new("raytracing", render_window(camera, world));
+    }
 }
 
-#[macroquad::main("raytracing")]
-async fn main() {
-    let mut camera = Camera::new();
+fn load_scene(path: &str) -> Result<(Camera, World), SceneError> {
+    let Scene { camera, world, split_method, light_sources, environment, fog } = scene::load_scene(path)?;
 
-    set_window_size(
-        camera.get_scaled_image_width() as u32, 
-        camera.get_scaled_image_height() as u32
-    );
+    let world: Arc<dyn Body> = match split_method {
+        Some(split_method) => Arc::new(BvhNode::new(&world, split_method)),
+        None => Arc::new(world),
+    };
+
+    let mut world = World::new(world).with_light_sources(light_sources);
+    if let Some(environment) = environment {
+        world = world.with_environment(environment);
+    }
+    if let Some((density, phase)) = fog {
+        world = world.with_fog(density, phase);
+    }
+
+    Ok((camera, world))
+}
 
+fn default_scene() -> (Camera, World) {
     let mut world = BodyList::new();
 
-    let plane = Rc::new(RefCell::new(Plane {
+    let plane = Arc::new(Plane {
         center: Vec3D::new(0.0, -1.0, 0.0),
         normal: Vec3D::y_unit(),
-        material: Rc::new(Lambertian { albedo: Vec3D::new(0.2, 1.0, 0.1) })
-    }));
+        material: Arc::new(Lambertian::new(Vec3D::new(0.2, 1.0, 0.1)))
+    });
 
-    let sphere = Rc::new(RefCell::new(Sphere {
+    let sphere = Arc::new(Sphere {
         center: Vec3D::zero(),
         radius: 1.0,
-        material: Rc::new(Lambertian { albedo: Vec3D::new(0.8, 0.2, 0.2) })
-    }));
-
-    let light = Rc::new(RefCell::new(Sphere {
-        center: Vec3D::new(-40.0, 40.0, 40.0), 
-        radius: 10.0,
-        material: Rc::new(Light { color: Vec3D::one() * 30.0 }),
-    }));
+        material: Arc::new(Lambertian::new(Vec3D::new(0.8, 0.2, 0.2)))
+    });
 
     world.push(plane.clone());
     world.push(sphere.clone());
-    world.push(light.clone());
+
+    let sun = Arc::new(DirectionalLight::new(Vec3D::new(1.0, -1.0, -1.0), Vec3D::one() * 2.0, 16.0));
+
+    let world = World::new(Arc::new(BvhNode::new(&world, SplitMethod::SurfaceAreaHeuristic)))
+        .with_light_sources(vec![sun]);
+
+    (Camera::new(), world)
+}
+
+fn render_headless(options: &Options, mut camera: Camera, world: &World) {
+    if let Some(samples) = options.samples {
+        camera.set_samples_per_pixel(samples);
+    }
+
+    let start = Instant::now();
+    camera.accumulate(world);
+    println!(
+        "rendered {}x{} at {} samples per pixel in {:.2?}",
+        camera.get_image_width(), camera.get_image_height(), camera.get_samples_per_pixel(), start.elapsed()
+    );
+
+    for output in &options.outputs {
+        if let Err(error) = write_image(camera.get_image(), output) {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+        println!("wrote {}", output);
+    }
+}
+
+async fn render_window(mut camera: Camera, world: World) {
+    set_window_size(
+        camera.get_scaled_image_width() as u32,
+        camera.get_scaled_image_height() as u32
+    );
 
     loop {
         camera.render(&world);
 
         next_frame().await
     }
-}
\ No newline at end of file
+}
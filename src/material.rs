@@ -2,10 +2,44 @@ use crate::{ray::Ray, vector::Vec3D, body::HitRecord};
 
 pub mod materials;
 
-pub trait Material {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool;
+/// Direction chosen by 'Material::sample' to continue a path in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScatterSample {
+    pub direction: Vec3D,
+    /// BSDF times the cosine term divided by 'pdf', or for delta lobes the
+    /// fraction of light carried along 'direction'.
+    pub weight: Vec3D,
+    /// Density per unit solid angle of choosing 'direction'. Zero for delta lobes.
+    pub pdf: f32,
+    /// Perfect mirror or refraction, which 'eval' and 'pdf' cannot represent.
+    pub is_delta: bool,
+}
 
-    fn emit(&self) -> Vec3D {
+pub trait Material: Send + Sync {
+    /// Picks the direction a path arriving along 'ray_in' continues in, or
+    /// 'None' if it is absorbed.
+    fn sample(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Option<ScatterSample> {
+        None
+    }
+
+    /// BSDF times the cosine term for light arriving from 'direction' and
+    /// leaving towards the origin of 'ray_in', so that it equals
+    /// 'weight * pdf' of a sample in that direction. Zero for delta lobes.
+    fn eval(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3D) -> Vec3D {
         Vec3D::zero()
     }
-}
\ No newline at end of file
+
+    /// Density per unit solid angle of 'sample' choosing 'direction'.
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3D) -> f32 {
+        0.0
+    }
+
+    fn emit(&self, _hit_record: &HitRecord) -> Vec3D {
+        Vec3D::zero()
+    }
+
+    /// Whether bodies made of this material are collected as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+}
@@ -1,43 +1,109 @@
-use std::ops::Neg;
+use std::{f32::consts::PI, ops::Neg, sync::Arc};
 
-use crate::random;
+use crate::{random, texture::{Texture, textures::SolidColor}};
 
 use super::*;
 
 pub struct Base {}
 
-impl Material for Base {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool {
-        true
+impl Material for Base {}
+
+pub struct Lambertian { pub albedo: Arc<dyn Texture> }
+
+impl Lambertian {
+    pub fn new(albedo: Vec3D) -> Self {
+        Self { albedo: Arc::new(SolidColor::new(albedo)) }
     }
 }
 
-pub struct Lambertian { pub albedo: Vec3D }
-
 impl Material for Lambertian {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool {
-        let mut scatter_direction = hit_record.normal + Vec3D::random_unit();
+    fn sample(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
+        let direction = Vec3D::random_cosine_direction(&hit_record.normal);
+
+        Some(ScatterSample {
+            direction,
+            weight: self.albedo.value(hit_record.u, hit_record.v, &hit_record.point),
+            pdf: direction.dot(&hit_record.normal).max(0.0) / PI,
+            is_delta: false,
+        })
+    }
 
-        if scatter_direction.is_near_zero() {
-            scatter_direction = hit_record.normal
+    fn eval(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> Vec3D {
+        let cos_theta = direction.unit().dot(&hit_record.normal);
+        if cos_theta <= 0.0 {
+            return Vec3D::zero()
         }
 
-        *ray_out = Ray::new(&hit_record.point, &scatter_direction);
-        *attenuation = self.albedo;
+        self.albedo.value(hit_record.u, hit_record.v, &hit_record.point) * cos_theta / PI
+    }
 
-        true
+    fn pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> f32 {
+        direction.unit().dot(&hit_record.normal).max(0.0) / PI
     }
 }
 
-pub struct Metal { pub albedo: Vec3D, pub fuzz: f32 }
+pub struct Metal { pub albedo: Arc<dyn Texture>, pub fuzz: f32 }
+
+impl Metal {
+    pub fn new(albedo: Vec3D, fuzz: f32) -> Self {
+        Self { albedo: Arc::new(SolidColor::new(albedo)), fuzz }
+    }
+
+    /// Density of 'reflected + fuzz * random_unit' pointing along the unit
+    /// vector 'direction'. 'reflected' must be a unit vector.
+    fn lobe_pdf(&self, reflected: &Vec3D, direction: &Vec3D) -> f32 {
+        // The offset is a uniform point on the sphere of radius 'fuzz' around
+        // 'reflected', so sum the density over the points where the line along
+        // 'direction' crosses that sphere.
+        let projection = direction.dot(reflected);
+        let discriminant = projection.powi(2) - 1.0 + self.fuzz.powi(2);
+
+        // The density is unbounded where the line grazes the sphere.
+        if discriminant <= f32::EPSILON {
+            return 0.0
+        }
+
+        let root = discriminant.sqrt();
+        let distances2: f32 = [projection - root, projection + root].into_iter()
+            .filter(|&distance| distance > 0.0)
+            .map(|distance| distance.powi(2))
+            .sum();
+
+        distances2 / (4.0 * PI * self.fuzz * root)
+    }
+}
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool {
+    fn sample(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
         let reflected = ray_in.direction.unit().reflect(&hit_record.normal);
-        *ray_out = Ray::new(&hit_record.point, &(reflected + self.fuzz * Vec3D::random_unit()));
-        *attenuation = self.albedo;
-        
-        ray_out.direction.dot(&hit_record.normal) > 0.0
+        let direction = reflected + self.fuzz * Vec3D::random_unit();
+
+        if direction.dot(&hit_record.normal) <= 0.0 {
+            return None
+        }
+
+        let is_delta = self.fuzz <= 0.0;
+
+        Some(ScatterSample {
+            direction,
+            weight: self.albedo.value(hit_record.u, hit_record.v, &hit_record.point),
+            pdf: if is_delta { 0.0 } else { self.lobe_pdf(&reflected, &direction.unit()) },
+            is_delta,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> Vec3D {
+        // 'sample' weighs every direction by 'albedo', so the BSDF follows the sampling density.
+        self.albedo.value(hit_record.u, hit_record.v, &hit_record.point) * self.pdf(ray_in, hit_record, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> f32 {
+        let direction = direction.unit();
+        if self.fuzz <= 0.0 || direction.dot(&hit_record.normal) <= 0.0 {
+            return 0.0
+        }
+
+        self.lobe_pdf(&ray_in.direction.unit().reflect(&hit_record.normal), &direction)
     }
 }
 
@@ -51,8 +117,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool {
-        *attenuation = Vec3D::one();
+    fn sample(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
         let refraction_ratio = if hit_record.front_face { 1.0 / self.refraction_index } 
             else { self.refraction_index };
 
@@ -68,20 +133,110 @@ impl Material for Dielectric {
             unit_direction.refract(&hit_record.normal, refraction_ratio)
         };
 
-        *ray_out = Ray::new(&hit_record.point, &direction);
+        Some(ScatterSample { direction, weight: Vec3D::one(), pdf: 0.0, is_delta: true })
+    }
+}
+
+pub struct Light { pub color: Arc<dyn Texture> }
+
+impl Light {
+    pub fn new(color: Vec3D) -> Self {
+        Self { color: Arc::new(SolidColor::new(color)) }
+    }
+}
 
+impl Material for Light {
+    fn emit(&self, hit_record: &HitRecord) -> Vec3D {
+        self.color.value(hit_record.u, hit_record.v, &hit_record.point)
+    }
+
+    fn is_emissive(&self) -> bool {
         true
     }
 }
 
-pub struct Light { pub color: Vec3D }
+/// Phase function scattering light equally in every direction, for media
+/// like 'ConstantMedium'. 'albedo' is the fraction of light scattered rather
+/// than absorbed.
+pub struct Isotropic { pub albedo: Arc<dyn Texture> }
 
-impl Material for Light {
-    fn scatter(&self, ray_in: &Ray, ray_out: &mut Ray, attenuation: &mut Vec3D, hit_record: &HitRecord) -> bool {
-        false
+impl Isotropic {
+    pub fn new(albedo: Vec3D) -> Self {
+        Self { albedo: Arc::new(SolidColor::new(albedo)) }
+    }
+}
+
+impl Material for Isotropic {
+    fn sample(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
+        Some(ScatterSample {
+            direction: Vec3D::random_unit(),
+            weight: self.albedo.value(hit_record.u, hit_record.v, &hit_record.point),
+            pdf: 1.0 / (4.0 * PI),
+            is_delta: false,
+        })
+    }
+
+    fn eval(&self, _ray_in: &Ray, hit_record: &HitRecord, _direction: &Vec3D) -> Vec3D {
+        self.albedo.value(hit_record.u, hit_record.v, &hit_record.point) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3D) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+/// Phase function of Henyey and Greenstein, scattering light mostly forward
+/// for a positive 'anisotropy' and mostly back for a negative one. Zero is
+/// the same as 'Isotropic'.
+pub struct HenyeyGreenstein { pub albedo: Arc<dyn Texture>, pub anisotropy: f32 }
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Vec3D, anisotropy: f32) -> Self {
+        Self { albedo: Arc::new(SolidColor::new(albedo)), anisotropy }
     }
 
-    fn emit(&self) -> Vec3D {
-        self.color
+    /// Density of scattering by an angle with cosine 'cos_theta' away from
+    /// the direction the light was travelling in.
+    fn phase(&self, cos_theta: f32) -> f32 {
+        let g = self.anisotropy;
+        let denominator = (1.0 + g.powi(2) - 2.0 * g * cos_theta).max(f32::EPSILON);
+
+        (1.0 - g.powi(2)) / (4.0 * PI * denominator * denominator.sqrt())
     }
-}
\ No newline at end of file
+}
+
+impl Material for HenyeyGreenstein {
+    fn sample(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterSample> {
+        let g = self.anisotropy;
+        let forward = ray_in.direction.unit();
+
+        // Inverts the cumulative distribution of the cosine of the angle.
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * random()
+        } else {
+            let ratio = (1.0 - g.powi(2)) / (1.0 - g + 2.0 * g * random());
+            ((1.0 + g.powi(2) - ratio.powi(2)) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+        let phi = 2.0 * PI * random();
+
+        let (tangent, bitangent) = forward.orthonormal_basis();
+
+        let direction = cos_theta * forward + sin_theta * (phi.cos() * tangent + phi.sin() * bitangent);
+
+        Some(ScatterSample {
+            direction,
+            weight: self.albedo.value(hit_record.u, hit_record.v, &hit_record.point),
+            pdf: self.phase(cos_theta),
+            is_delta: false,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3D) -> Vec3D {
+        self.albedo.value(hit_record.u, hit_record.v, &hit_record.point) * self.pdf(ray_in, hit_record, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, _hit_record: &HitRecord, direction: &Vec3D) -> f32 {
+        self.phase(ray_in.direction.unit().dot(&direction.unit()))
+    }
+}
@@ -0,0 +1,425 @@
+use std::{sync::Arc, collections::HashMap, fmt, fs, io, path::{Path, PathBuf}};
+
+use crate::{
+    body::{body_list::BodyList, mesh::{MeshBuffers, MeshFace, MeshVertex, TriangleMesh}},
+    material::{Material, materials::{Lambertian, Metal, Dielectric, Light}},
+    vector::Vec3D,
+};
+
+const DEFAULT_ALBEDO: Vec3D = Vec3D::new(0.8, 0.8, 0.8);
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            Self::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Loads a Wavefront OBJ file along with any MTL libraries it references.
+///
+/// Every group and material combination becomes its own 'TriangleMesh', all
+/// sharing the same vertex buffers. Polygons are fan triangulated, so they
+/// are expected to be convex. Faces without a material, or with one that no
+/// library defines, like the placeholder names many exporters write, are a
+/// light gray 'Lambertian'.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<BodyList, ObjError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut buffers = MeshBuffers::default();
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let default_material: Arc<dyn Material> = Arc::new(Lambertian::new(DEFAULT_ALBEDO));
+
+    let mut group = String::new();
+    let mut material_name: Option<String> = None;
+    let mut meshes: Vec<ObjMesh> = vec![];
+
+    for (index, line) in source.lines().enumerate() {
+        let mut parser = LineParser::new(path, index + 1, line);
+
+        let Some(keyword) = parser.keyword() else {
+            continue
+        };
+
+        match keyword {
+            "v" => buffers.positions.push(parser.vector()?),
+            "vn" => buffers.normals.push(parser.vector()?),
+            "vt" => {
+                let u = parser.number()?;
+                let v = parser.optional_number()?.unwrap_or(0.0);
+                buffers.uvs.push((u, v));
+            },
+            "f" => {
+                let mut polygon = vec![];
+                while let Some(token) = parser.next() {
+                    polygon.push(parser.face_vertex(token, &buffers)?);
+                }
+
+                if polygon.len() < 3 {
+                    return Err(parser.error("face needs at least three vertices"))
+                }
+
+                let faces = match meshes.iter_mut().find(|mesh| mesh.group == group && mesh.material == material_name) {
+                    Some(mesh) => &mut mesh.faces,
+                    None => {
+                        meshes.push(ObjMesh { group: group.clone(), material: material_name.clone(), faces: vec![] });
+                        &mut meshes.last_mut().unwrap().faces
+                    },
+                };
+
+                for i in 1..polygon.len() - 1 {
+                    faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+                }
+            },
+            "g" | "o" => group = parser.rest().to_string(),
+            // Resolved once the whole file is read, so libraries may come later.
+            "usemtl" => material_name = Some(parser.rest().to_string()).filter(|name| !name.is_empty()),
+            "mtllib" => {
+                while let Some(file) = parser.next() {
+                    materials.extend(load_mtl(directory.join(file))?);
+                }
+            },
+            // Smoothing groups, lines, points and other statements are not supported.
+            _ => {},
+        }
+    }
+
+    let buffers = Arc::new(buffers);
+    let mut list = BodyList::new();
+
+    for ObjMesh { material, faces, .. } in meshes {
+        let material = material
+            .and_then(|name| materials.get(&name).cloned())
+            .unwrap_or_else(|| default_material.clone());
+
+        list.push(Arc::new(TriangleMesh::new(buffers.clone(), faces, material)));
+    }
+
+    Ok(list)
+}
+
+/// Loads the materials of an MTL library.
+///
+/// A non-zero 'Ke' makes a 'Light', 'illum' 4, 6, 7 or 9 a 'Dielectric' with
+/// index 'Ni', 'illum' 3, 5 or 8 a 'Metal' colored by 'Ks' with fuzz derived
+/// from 'Ns', and anything else a 'Lambertian' colored by 'Kd'.
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlParameters)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let mut parser = LineParser::new(path, index + 1, line);
+
+        let Some(keyword) = parser.keyword() else {
+            continue
+        };
+
+        if keyword == "newmtl" {
+            let name = parser.name()?.to_string();
+            if let Some((name, parameters)) = current.replace((name, MtlParameters::default())) {
+                materials.insert(name, parameters.into_material());
+            }
+            continue
+        }
+
+        let Some((_, parameters)) = current.as_mut() else {
+            return Err(parser.error(&format!("'{}' before any 'newmtl'", keyword)))
+        };
+
+        match keyword {
+            "Kd" => parameters.diffuse = parser.color()?,
+            "Ks" => parameters.specular = parser.color()?,
+            "Ke" => parameters.emission = parser.color()?,
+            "Ni" => parameters.refraction_index = parser.number()?,
+            "Ns" => parameters.shininess = parser.number()?,
+            "illum" => {
+                let token = parser.next().ok_or_else(|| parser.error("expected illumination model"))?;
+                parameters.illumination = token.parse()
+                    .map_err(|_| parser.error(&format!("expected illumination model, found '{}'", token)))?;
+            },
+            // Textures, transparency and other statements are not supported.
+            _ => {},
+        }
+    }
+
+    if let Some((name, parameters)) = current {
+        materials.insert(name, parameters.into_material());
+    }
+
+    Ok(materials)
+}
+
+/// Faces sharing a group and material.
+struct ObjMesh {
+    group: String,
+    material: Option<String>,
+    faces: Vec<MeshFace>,
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io { path: path.to_path_buf(), error })
+}
+
+struct MtlParameters {
+    diffuse: Vec3D,
+    specular: Vec3D,
+    emission: Vec3D,
+    refraction_index: f32,
+    shininess: f32,
+    illumination: u32,
+}
+
+impl Default for MtlParameters {
+    fn default() -> Self {
+        Self {
+            diffuse: DEFAULT_ALBEDO,
+            specular: Vec3D::zero(),
+            emission: Vec3D::zero(),
+            refraction_index: 1.0,
+            shininess: 0.0,
+            illumination: 2,
+        }
+    }
+}
+
+impl MtlParameters {
+    fn into_material(self) -> Arc<dyn Material> {
+        if !self.emission.is_near_zero() {
+            return Arc::new(Light::new(self.emission))
+        }
+
+        match self.illumination {
+            4 | 6 | 7 | 9 => Arc::new(Dielectric { refraction_index: self.refraction_index }),
+            3 | 5 | 8 => Arc::new(Metal::new(
+                self.specular,
+                // Approximate roughness of a Blinn-Phong lobe with exponent 'Ns'.
+                (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt(),
+            )),
+            _ => Arc::new(Lambertian::new(self.diffuse)),
+        }
+    }
+}
+
+struct LineParser<'a> {
+    path: &'a Path,
+    line: usize,
+    rest: &'a str,
+}
+
+impl<'a> LineParser<'a> {
+    fn new(path: &'a Path, line: usize, source: &'a str) -> Self {
+        let source = source.split('#').next().unwrap_or("");
+
+        Self { path, line, rest: source.trim() }
+    }
+
+    fn error(&self, message: &str) -> ObjError {
+        ObjError::Parse { path: self.path.to_path_buf(), line: self.line, message: message.to_string() }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            return None
+        }
+
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (token, rest) = rest.split_at(end);
+        self.rest = rest;
+
+        Some(token)
+    }
+
+    fn keyword(&mut self) -> Option<&'a str> {
+        self.next()
+    }
+
+    fn rest(&self) -> &'a str {
+        self.rest.trim()
+    }
+
+    fn name(&mut self) -> Result<&'a str, ObjError> {
+        match self.rest() {
+            "" => Err(self.error("expected name")),
+            name => Ok(name),
+        }
+    }
+
+    fn optional_number(&mut self) -> Result<Option<f32>, ObjError> {
+        match self.next() {
+            Some(token) => token.parse()
+                .map(Some)
+                .map_err(|_| self.error(&format!("expected number, found '{}'", token))),
+            None => Ok(None),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, ObjError> {
+        self.optional_number()?.ok_or_else(|| self.error("expected number"))
+    }
+
+    fn vector(&mut self) -> Result<Vec3D, ObjError> {
+        Ok(Vec3D::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    /// Colors may be given as a single gray value.
+    fn color(&mut self) -> Result<Vec3D, ObjError> {
+        let r = self.number()?;
+
+        match self.optional_number()? {
+            Some(g) => Ok(Vec3D::new(r, g, self.number()?)),
+            None => Ok(Vec3D::new(r, r, r)),
+        }
+    }
+
+    /// Parses 'v', 'v/vt', 'v//vn' or 'v/vt/vn', resolving negative indices.
+    fn face_vertex(&self, token: &str, buffers: &MeshBuffers) -> Result<MeshVertex, ObjError> {
+        let mut parts = token.split('/');
+
+        let resolve = |part: Option<&str>, count: usize, kind: &str| -> Result<Option<usize>, ObjError> {
+            let part = match part {
+                None | Some("") => return Ok(None),
+                Some(part) => part,
+            };
+
+            let index: i64 = part.parse()
+                .map_err(|_| self.error(&format!("expected {} index, found '{}'", kind, part)))?;
+
+            let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+
+            if index == 0 || resolved < 0 || resolved >= count as i64 {
+                return Err(self.error(&format!("{} index {} out of range", kind, index)))
+            }
+
+            Ok(Some(resolved as usize))
+        };
+
+        let position = resolve(parts.next(), buffers.positions.len(), "vertex")?
+            .ok_or_else(|| self.error(&format!("missing vertex index in '{}'", token)))?;
+        let uv = resolve(parts.next(), buffers.uvs.len(), "texture coordinate")?;
+        let normal = resolve(parts.next(), buffers.normals.len(), "normal")?;
+
+        Ok(MeshVertex { position, normal, uv })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::{Body, HitRecord}, ray::Ray, interval::Interval};
+
+    /// Writes 'files' to a fresh directory named after 'test' and returns its path.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("raytracing-obj-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        for (name, contents) in files {
+            fs::write(directory.join(name), contents).unwrap();
+        }
+
+        directory
+    }
+
+    const SQUARE: &str = "v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\n";
+
+    /// Material of whatever the ray straight down the z axis hits first.
+    fn material_at_origin(list: &BodyList) -> Arc<dyn Material> {
+        let ray = Ray::new(&Vec3D::new(0.1, 0.2, 5.0), &Vec3D::new(0.0, 0.0, -1.0));
+        let mut hit_record = HitRecord::new();
+        assert!(list.hit(&ray, Interval::new(0.001, f32::INFINITY), &mut hit_record));
+
+        hit_record.material
+    }
+
+    fn parse_error(test: &str, source: &str) -> String {
+        let directory = write_files(test, &[("model.obj", source)]);
+        let error = load_obj(directory.join("model.obj")).err().expect("load should fail");
+
+        assert!(matches!(error, ObjError::Parse { .. }));
+        error.to_string()
+    }
+
+    #[test]
+    fn triangulates_polygons() {
+        let directory = write_files("polygons", &[("model.obj", &format!("{}f 1 2 3 4\n", SQUARE))]);
+        let list = load_obj(directory.join("model.obj")).unwrap();
+
+        assert_eq!(list.len(), 1);
+        assert!(!material_at_origin(&list).is_emissive());
+    }
+
+    #[test]
+    fn resolves_materials_declared_after_use() {
+        let directory = write_files("materials", &[
+            ("model.obj", &format!("{}usemtl lamp\nf 1 2 3 4\nmtllib model.mtl\n", SQUARE)),
+            ("model.mtl", "newmtl lamp\nKe 4 4 4\n"),
+        ]);
+        let list = load_obj(directory.join("model.obj")).unwrap();
+
+        assert!(material_at_origin(&list).is_emissive());
+    }
+
+    #[test]
+    fn unknown_materials_fall_back_to_the_default() {
+        let directory = write_files("unknown", &[("model.obj", &format!("{}usemtl None\nf 1 2 3\nusemtl\nf 1 3 4\n", SQUARE))]);
+        let list = load_obj(directory.join("model.obj")).unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert!(!material_at_origin(&list).is_emissive());
+    }
+
+    #[test]
+    fn reports_the_line_of_parse_errors() {
+        let message = parse_error("short", &format!("{}f 1 2\n", SQUARE));
+        assert!(message.ends_with("model.obj:5: face needs at least three vertices"), "{}", message);
+
+        let message = parse_error("range", &format!("{}\nf 1 2 5\n", SQUARE));
+        assert!(message.ends_with("model.obj:6: vertex index 5 out of range"), "{}", message);
+
+        let message = parse_error("index", "v 0 0 0\nf 1 x 1\n");
+        assert!(message.ends_with("model.obj:2: expected vertex index, found 'x'"), "{}", message);
+
+        let message = parse_error("number", "v 0 zero 0\n");
+        assert!(message.contains("model.obj:1:"), "{}", message);
+    }
+
+    #[test]
+    fn reports_errors_in_material_libraries() {
+        let directory = write_files("library", &[
+            ("model.obj", "mtllib broken.mtl\n"),
+            ("broken.mtl", "Kd 1 1 1\n"),
+        ]);
+        let message = load_obj(directory.join("model.obj")).err().unwrap().to_string();
+
+        assert!(message.ends_with("broken.mtl:1: 'Kd' before any 'newmtl'"), "{}", message);
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let directory = write_files("missing", &[("model.obj", "mtllib absent.mtl\n")]);
+        let error = load_obj(directory.join("model.obj")).err().unwrap();
+
+        assert!(matches!(error, ObjError::Io { ref path, .. } if path.ends_with("absent.mtl")));
+    }
+}
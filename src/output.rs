@@ -0,0 +1,47 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use macroquad::texture::Image;
+
+/// Writes 'image' as PNG or PPM depending on the extension of 'path'.
+pub fn write_image(image: &Image, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("png") => write_png(image, path),
+        Some("ppm") => write_ppm(image, path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: unsupported image format, expected .png or .ppm", path.display())
+        )),
+    }
+}
+
+/// Plain text PPM (P3).
+pub fn write_ppm(image: &Image, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "P3\n{} {}\n255", image.width, image.height)?;
+
+    for [r, g, b, _] in image.get_image_data() {
+        writeln!(writer, "{} {} {}", r, g, b)?;
+    }
+
+    writer.flush()
+}
+
+/// 8-bit RGB PNG.
+pub fn write_png(image: &Image, path: impl AsRef<Path>) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(writer, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = image.get_image_data().iter()
+        .flat_map(|&[r, g, b, _]| [r, g, b])
+        .collect();
+
+    encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(io::Error::other)
+}
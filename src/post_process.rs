@@ -0,0 +1,80 @@
+use macroquad::{texture::Image, color::Color};
+
+use crate::{vector::Vec3D, film::Film};
+
+/// Operator compressing scene radiance into the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Clips every channel to 1.
+    Clamp,
+    /// 'c / (1 + c)' per channel.
+    Reinhard,
+    /// Reinhard that maps 'white_point' and above to 1 instead of approaching it.
+    ExtendedReinhard { white_point: f32 },
+    /// Narkowicz's fit of the ACES filmic curve.
+    AcesFilmic,
+}
+
+impl ToneMap {
+    pub fn apply(&self, color: Vec3D) -> Vec3D {
+        let channel = |c: f32| -> f32 {
+            let c = c.max(0.0);
+
+            match *self {
+                Self::Clamp => c,
+                Self::Reinhard => c / (1.0 + c),
+                Self::ExtendedReinhard { white_point } => c * (1.0 + c / white_point.powi(2)) / (1.0 + c),
+                Self::AcesFilmic => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+            }.min(1.0)
+        };
+
+        Vec3D::new(channel(color.x), channel(color.y), channel(color.z))
+    }
+}
+
+/// Turns linear radiance into display colors: exposure, then tone mapping,
+/// then sRGB encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostProcess {
+    /// In stops, so each unit doubles the brightness.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    /// Gamma encode for sRGB displays and files. Disable to keep linear output.
+    pub srgb: bool,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        Self { exposure: 0.0, tone_map: ToneMap::AcesFilmic, srgb: true }
+    }
+}
+
+impl PostProcess {
+    /// Maps linear radiance to a display color with channels in [0, 1].
+    pub fn apply(&self, radiance: Vec3D) -> Vec3D {
+        let color = self.tone_map.apply(radiance * self.exposure.exp2());
+
+        if self.srgb {
+            Vec3D::new(srgb_encode(color.x), srgb_encode(color.y), srgb_encode(color.z))
+        } else {
+            color
+        }
+    }
+
+    /// Writes the processed pixels of 'film' to 'destination', which must be the same size.
+    pub fn develop(&self, film: &Film, destination: &mut Image) {
+        for (radiance, output) in film.pixels().zip(destination.get_image_data_mut()) {
+            let color = self.apply(radiance);
+
+            *output = Color::new(color.x, color.y, color.z, 1.0).into();
+        }
+    }
+}
+
+fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,1432 @@
+use std::{collections::HashMap, fmt, fs, io, ops::Range, path::{Path, PathBuf}, sync::Arc};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    body::{Body, body_list::BodyList, bodies::{Sphere, Plane, Triangle, Quad, Disk, Cuboid, Cylinder, Cone, Torus, Capsule}, bvh::SplitMethod, instance::Instance, csg::{Csg, CsgOperation}, sdf::SdfBody, medium::ConstantMedium, volume::{VoxelGrid, GridVolume}, motion::{Motion, Keyframe}},
+    camera::{Camera, CameraSettings},
+    environment::{Environment, environments::{UniformEnvironment, EnvironmentMap}, sky::Sky},
+    integrator::Integrator,
+    light::{LightSource, lights::{PointLight, SpotLight, DirectionalLight}},
+    material::{Material, materials::{Lambertian, Metal, Dielectric, Light, Isotropic, HenyeyGreenstein}},
+    obj::{self, ObjError},
+    post_process::{PostProcess, ToneMap},
+    transform::Transform,
+    sdf::{
+        Sdf,
+        primitives::{SphereSdf, RoundedBoxSdf, TorusSdf, CapsuleSdf, Mandelbulb},
+        combinators::{SmoothUnion, Twist, Repetition},
+    },
+    texture::{
+        Texture,
+        textures::{SolidColor, Checker, UvChecker, ImageTexture, WrapMode},
+        perlin::{NoiseTexture, NoisePattern},
+    },
+    vector::Vec3D,
+};
+
+/// Everything needed to render a scene file.
+pub struct Scene {
+    pub camera: Camera,
+    pub world: BodyList,
+    /// 'None' when the scene asks for bodies to be traced without a BVH.
+    pub split_method: Option<SplitMethod>,
+    pub light_sources: Vec<Arc<dyn LightSource>>,
+    /// 'None' keeps the default background.
+    pub environment: Option<Arc<dyn Environment>>,
+    /// Density and phase function of fog between the bodies, if any.
+    pub fog: Option<(f32, Arc<dyn Material>)>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, line: usize, column: usize, message: String },
+    Obj(ObjError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            Self::Parse { path, line, column, message } => {
+                write!(f, "{}:{}:{}: {}", path.display(), line, column, message)
+            },
+            Self::Obj(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            Self::Parse { .. } => None,
+            Self::Obj(error) => Some(error),
+        }
+    }
+}
+
+/// Loads a TOML scene file. Paths inside it are relative to the file.
+///
+/// ```toml
+/// [camera]
+/// look_from = [0, 0, 2]
+/// vertical_field_of_view = 90
+/// shutter_open = 0 # rays are traced at times in between, blurring moving bodies
+/// shutter_close = 1
+///
+/// [render]
+/// samples_per_pixel = 100
+/// min_bounces = 3
+/// max_bounces = 50
+/// acceleration = "sah" # or "median" or "none"
+/// exposure = 0
+/// tone_map = "aces" # or "clamp", "reinhard" or "extended_reinhard"
+///
+/// [environment]
+/// type = "map" # or "uniform" with a 'color', or "sky" with a 'sun_direction' and 'turbidity'
+/// path = "studio.hdr" # or a .pfm
+/// rotation = 90
+/// intensity = 1
+///
+/// [textures.tiles]
+/// type = "checker" # or "solid", "uv_checker", "image" or "noise"
+/// scale = 0.5
+/// even = [0.9, 0.9, 0.9]
+/// odd = "marble" # textures may use textures defined above them
+///
+/// [materials.red]
+/// type = "lambertian" # or "metal", "dielectric" or "light"
+/// albedo = [0.8, 0.2, 0.2] # or the name of a texture
+///
+/// [materials.smoke]
+/// type = "henyey_greenstein" # or "isotropic", phase functions for media and fog
+/// albedo = [0.9, 0.9, 0.9]
+/// anisotropy = 0.6 # scattering mostly forward
+///
+/// [fog]
+/// density = 0.02
+/// material = "smoke"
+///
+/// [[bodies]]
+/// type = "sphere" # or "plane", "triangle", "quad", "disk", "box", "cylinder", "cone", "torus", "capsule" or "obj"
+/// center = [0, 0, 0]
+/// radius = 1
+/// material = "red"
+///
+/// [[bodies]]
+/// type = "quad"
+/// corner = [-1, 2, -1]
+/// edges = [[2, 0, 0], [0, 0, 2]] # the front faces along their cross product
+/// material = "lamp"
+///
+/// [[bodies]]
+/// type = "box"
+/// corners = [[-1, -1, -1], [1, 1, 1]]
+/// material = "red"
+/// scale = 0.5 # or per axis, like 'translate'; any body can be transformed
+/// rotate = [0, 45, 0] # degrees around x, y and z
+/// translate = [2, 0, 0]
+///
+/// [[bodies]]
+/// type = "sphere"
+/// center = [0, 0, 0]
+/// radius = 0.5
+/// material = "red"
+/// motion = [ # any body can move, after it is transformed
+///     { time = 0 },
+///     { time = 1, translate = [1, 0, 0], rotate = [0, 90, 0], scale = 1.5 },
+/// ]
+///
+/// [[bodies]]
+/// type = "cone" # "cylinder" and "capsule" take no 'top_radius'
+/// center = [0, 1, 0]
+/// radius = 0.5
+/// top_radius = 0.2 # 0 or left out for a pointed cone
+/// height = 1
+/// material = "red"
+///
+/// [[bodies]]
+/// type = "torus"
+/// center = [0, 0, 0]
+/// radius = 1
+/// tube_radius = 0.25
+/// material = "red"
+///
+/// [[bodies]]
+/// type = "difference" # or "union" or "intersection", of closed bodies, not triangles, quads, disks or meshes
+/// operands = [ # the first minus all the others
+///     { type = "sphere", center = [0, 0, 0], radius = 1, material = "red" },
+///     { type = "cylinder", center = [0, 0, 0], radius = 0.3, height = 3, material = "red" },
+/// ]
+///
+/// [[bodies]]
+/// type = "sdf"
+/// material = "red"
+/// max_steps = 256
+/// epsilon = 0.0001
+/// # "sphere", "rounded_box" with a 'size' and 'rounding', "torus", "capsule",
+/// # "mandelbulb" with a 'power' and 'iterations', "smooth_union" of 'operands',
+/// # "twist" of an 'sdf' by a 'rate' in degrees per unit of height, or "repeat"
+/// # of an 'sdf' 'count' times along each axis, 'period' apart
+/// sdf = { type = "smooth_union", smoothness = 0.3, operands = [
+///     { type = "sphere", center = [-0.5, 0, 0], radius = 0.6 },
+///     { type = "rounded_box", center = [0.5, 0, 0], size = [1, 1, 1], rounding = 0.1 },
+/// ] }
+///
+/// [[bodies]]
+/// type = "medium"
+/// density = 2
+/// material = "smoke"
+/// boundary = { type = "sphere", center = [0, 0, 0], radius = 1 } # of any closed body, as for CSG
+///
+/// [[bodies]]
+/// type = "volume"
+/// path = "smoke.raw" # or a .grid, the density throughout the box
+/// corners = [[-1, 0, -1], [1, 2, 1]]
+/// density = 10 # scales the grid
+/// material = "smoke"
+/// emission = [4, 1.5, 0.3] # optional glow, scaled by the grid in 'emission_path',
+/// emission_path = "temperature.raw" # or by the density if that is not given
+///
+/// [[lights]]
+/// type = "spot" # or "point" or "directional"
+/// position = [0, 4, 0]
+/// direction = [0, -1, 0]
+/// intensity = [20, 20, 20]
+/// inner_angle = 20
+/// outer_angle = 30
+/// ```
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)
+        .map_err(|error| SceneError::Io { path: path.to_path_buf(), error })?;
+
+    SceneLoader { path, source: &source }.load()
+}
+
+struct SceneLoader<'a> {
+    path: &'a Path,
+    source: &'a str,
+}
+
+impl SceneLoader<'_> {
+    fn load(&self) -> Result<Scene, SceneError> {
+        let file: SceneFile = toml::from_str(self.source)
+            .map_err(|error| self.error(error.span().unwrap_or(0..0), error.message()))?;
+
+        let mut camera = self.camera(&file)?;
+        camera.set_post_process(self.post_process(file.render.as_ref())?);
+        camera.set_integrator(self.integrator(file.render.as_ref())?);
+        let split_method = self.split_method(file.render.as_ref())?;
+
+        // Validated in file order so the first error reported is the first in the file.
+        let mut definitions: Vec<_> = file.textures.iter().collect();
+        definitions.sort_by_key(|(_, description)| description.span().start);
+
+        let mut textures = HashMap::new();
+        for (name, description) in definitions {
+            let texture = self.texture(description, &textures)?;
+            textures.insert(name.as_str(), texture);
+        }
+
+        let mut definitions: Vec<_> = file.materials.iter().collect();
+        definitions.sort_by_key(|(_, description)| description.span().start);
+
+        let mut materials = HashMap::new();
+        for (name, description) in definitions {
+            materials.insert(name.as_str(), self.material(description, &textures)?);
+        }
+
+        let mut world = BodyList::new();
+        let mut meshes = HashMap::new();
+        for description in &file.bodies {
+            world.push(self.body(description, None, &materials, &mut meshes)?);
+        }
+
+        let light_sources = file.lights.iter()
+            .map(|description| self.light_source(description))
+            .collect::<Result<_, _>>()?;
+
+        let environment = file.environment.as_ref()
+            .map(|description| self.environment(description))
+            .transpose()?;
+
+        let fog = file.fog.as_ref()
+            .map(|description| self.fog(description, &materials))
+            .transpose()?;
+
+        Ok(Scene { camera, world, split_method, light_sources, environment, fog })
+    }
+
+    fn error(&self, span: Range<usize>, message: &str) -> SceneError {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+        SceneError::Parse { path: self.path.to_path_buf(), line, column, message: message.to_string() }
+    }
+
+    fn camera(&self, file: &SceneFile) -> Result<Camera, SceneError> {
+        let (empty_camera, empty_render) = (CameraDescription::default(), RenderDescription::default());
+        let (span, camera) = file.camera.as_ref().map_or((0..0, &empty_camera), |camera| (camera.span(), camera.get_ref()));
+        let (render_span, render) = file.render.as_ref().map_or((0..0, &empty_render), |render| (render.span(), render.get_ref()));
+        let defaults = CameraSettings::default();
+
+        let settings = CameraSettings {
+            aspect_ratio: camera.aspect_ratio.unwrap_or(defaults.aspect_ratio),
+            image_width: camera.image_width.unwrap_or(defaults.image_width),
+            vertical_field_of_view: camera.vertical_field_of_view.unwrap_or(defaults.vertical_field_of_view),
+            look_from: camera.look_from.map(vector).unwrap_or(defaults.look_from),
+            look_to: camera.look_to.map(vector).unwrap_or(defaults.look_to),
+            view_up: camera.view_up.map(vector).unwrap_or(defaults.view_up),
+            defocus_angle: camera.defocus_angle.unwrap_or(defaults.defocus_angle),
+            focus_distance: camera.focus_distance.unwrap_or(defaults.focus_distance),
+            samples_per_pixel: render.samples_per_pixel.unwrap_or(defaults.samples_per_pixel),
+            image_scaling: camera.image_scaling.unwrap_or(defaults.image_scaling),
+            shutter_open: camera.shutter_open.unwrap_or(defaults.shutter_open),
+            shutter_close: camera.shutter_close.or(camera.shutter_open).unwrap_or(defaults.shutter_close),
+        };
+
+        if settings.aspect_ratio <= 0.0 {
+            return Err(self.error(span, "'aspect_ratio' must be positive"))
+        }
+        if settings.image_width == 0 {
+            return Err(self.error(span, "'image_width' must be positive"))
+        }
+        if !(0.0..180.0).contains(&settings.vertical_field_of_view) || settings.vertical_field_of_view == 0.0 {
+            return Err(self.error(span, "'vertical_field_of_view' must be between 0 and 180 degrees"))
+        }
+        if settings.look_from == settings.look_to {
+            return Err(self.error(span, "'look_from' and 'look_to' must differ"))
+        }
+        if settings.focus_distance <= 0.0 {
+            return Err(self.error(span, "'focus_distance' must be positive"))
+        }
+        if settings.image_scaling <= 0.0 {
+            return Err(self.error(span, "'image_scaling' must be positive"))
+        }
+        if settings.shutter_close < settings.shutter_open {
+            return Err(self.error(span, "'shutter_close' must not be before 'shutter_open'"))
+        }
+        if settings.samples_per_pixel == 0 {
+            return Err(self.error(render_span, "'samples_per_pixel' must be positive"))
+        }
+
+        Ok(Camera::with_settings(settings))
+    }
+
+    fn post_process(&self, render: Option<&Spanned<RenderDescription>>) -> Result<PostProcess, SceneError> {
+        let defaults = PostProcess::default();
+
+        let Some(render) = render else {
+            return Ok(defaults)
+        };
+
+        let description = render.get_ref();
+
+        let tone_map = match &description.tone_map {
+            None => defaults.tone_map,
+            Some(tone_map) => match tone_map.get_ref().as_str() {
+                "clamp" => ToneMap::Clamp,
+                "reinhard" => ToneMap::Reinhard,
+                "extended_reinhard" => ToneMap::ExtendedReinhard { white_point: description.white_point.unwrap_or(4.0) },
+                "aces" => ToneMap::AcesFilmic,
+                other => return Err(self.error(
+                    tone_map.span(),
+                    &format!("unknown tone map '{}', expected 'clamp', 'reinhard', 'extended_reinhard' or 'aces'", other)
+                )),
+            },
+        };
+
+        if let ToneMap::ExtendedReinhard { white_point } = tone_map {
+            if white_point <= 0.0 {
+                return Err(self.error(render.span(), "'white_point' must be positive"))
+            }
+        } else if description.white_point.is_some() {
+            return Err(self.error(render.span(), "'white_point' only applies to 'extended_reinhard'"))
+        }
+
+        Ok(PostProcess {
+            exposure: description.exposure.unwrap_or(defaults.exposure),
+            tone_map,
+            srgb: description.srgb.unwrap_or(defaults.srgb),
+        })
+    }
+
+    fn integrator(&self, render: Option<&Spanned<RenderDescription>>) -> Result<Integrator, SceneError> {
+        let defaults = Integrator::default();
+
+        let Some(render) = render else {
+            return Ok(defaults)
+        };
+
+        let integrator = Integrator {
+            min_bounces: render.get_ref().min_bounces.unwrap_or(defaults.min_bounces),
+            max_bounces: render.get_ref().max_bounces.unwrap_or(defaults.max_bounces),
+        };
+
+        if integrator.min_bounces > integrator.max_bounces {
+            return Err(self.error(render.span(), "'min_bounces' must not exceed 'max_bounces'"))
+        }
+
+        Ok(integrator)
+    }
+
+    fn split_method(&self, render: Option<&Spanned<RenderDescription>>) -> Result<Option<SplitMethod>, SceneError> {
+        let Some(acceleration) = render.and_then(|render| render.get_ref().acceleration.as_ref()) else {
+            return Ok(Some(SplitMethod::SurfaceAreaHeuristic))
+        };
+
+        match acceleration.get_ref().as_str() {
+            "sah" => Ok(Some(SplitMethod::SurfaceAreaHeuristic)),
+            "median" => Ok(Some(SplitMethod::Median)),
+            "none" => Ok(None),
+            other => Err(self.error(
+                acceleration.span(),
+                &format!("unknown acceleration '{}', expected 'sah', 'median' or 'none'", other)
+            )),
+        }
+    }
+
+    fn texture(
+        &self,
+        description: &Spanned<TextureDescription>,
+        textures: &HashMap<&str, Arc<dyn Texture>>
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let texture = description.get_ref();
+        let kind = texture.kind.get_ref().as_str();
+
+        let color = |name: &str, color: Option<&ColorDescription>| -> Result<Arc<dyn Texture>, SceneError> {
+            fields.color(fields.require(name, color)?, textures)
+        };
+
+        let texture: Arc<dyn Texture> = match kind {
+            "solid" => {
+                fields.only(kind, &[("scale", texture.scale.is_some()), ("even", texture.even.is_some()), ("odd", texture.odd.is_some()), ("columns", texture.columns.is_some()), ("rows", texture.rows.is_some()), ("path", texture.path.is_some()), ("wrap", texture.wrap.is_some()), ("pattern", texture.pattern.is_some())])?;
+                Arc::new(SolidColor::new(vector(fields.require("color", texture.color)?)))
+            },
+            "checker" => {
+                fields.only(kind, &[("color", texture.color.is_some()), ("columns", texture.columns.is_some()), ("rows", texture.rows.is_some()), ("path", texture.path.is_some()), ("wrap", texture.wrap.is_some()), ("pattern", texture.pattern.is_some())])?;
+                let scale = texture.scale.unwrap_or(1.0);
+                if scale <= 0.0 {
+                    return Err(fields.error("'scale' must be positive"))
+                }
+                Arc::new(Checker { scale, even: color("even", texture.even.as_ref())?, odd: color("odd", texture.odd.as_ref())? })
+            },
+            "uv_checker" => {
+                fields.only(kind, &[("color", texture.color.is_some()), ("scale", texture.scale.is_some()), ("path", texture.path.is_some()), ("wrap", texture.wrap.is_some()), ("pattern", texture.pattern.is_some())])?;
+                let (columns, rows) = (fields.require("columns", texture.columns)?, fields.require("rows", texture.rows)?);
+                if columns <= 0.0 || rows <= 0.0 {
+                    return Err(fields.error("'columns' and 'rows' must be positive"))
+                }
+                Arc::new(UvChecker { columns, rows, even: color("even", texture.even.as_ref())?, odd: color("odd", texture.odd.as_ref())? })
+            },
+            "image" => {
+                fields.only(kind, &[("color", texture.color.is_some()), ("scale", texture.scale.is_some()), ("even", texture.even.is_some()), ("odd", texture.odd.is_some()), ("columns", texture.columns.is_some()), ("rows", texture.rows.is_some()), ("pattern", texture.pattern.is_some())])?;
+                let wrap = match texture.wrap.as_ref().map(|wrap| (wrap.span(), wrap.get_ref().as_str())) {
+                    None | Some((_, "repeat")) => WrapMode::Repeat,
+                    Some((_, "clamp")) => WrapMode::Clamp,
+                    Some((_, "mirror")) => WrapMode::Mirror,
+                    Some((span, other)) => return Err(self.error(
+                        span,
+                        &format!("unknown wrap mode '{}', expected 'repeat', 'clamp' or 'mirror'", other)
+                    )),
+                };
+                let path = self.path.parent().unwrap_or(Path::new("")).join(fields.require("path", texture.path.as_ref())?);
+                let image = ImageTexture::load(&path).map_err(|error| SceneError::Io { path, error })?;
+                Arc::new(image.with_wrap(wrap))
+            },
+            "noise" => {
+                fields.only(kind, &[("even", texture.even.is_some()), ("odd", texture.odd.is_some()), ("columns", texture.columns.is_some()), ("rows", texture.rows.is_some()), ("path", texture.path.is_some()), ("wrap", texture.wrap.is_some())])?;
+                let pattern = match texture.pattern.as_ref().map(|pattern| (pattern.span(), pattern.get_ref().as_str())) {
+                    None | Some((_, "noise")) => NoisePattern::Noise,
+                    Some((_, "turbulence")) => NoisePattern::Turbulence,
+                    Some((_, "marble")) => NoisePattern::Marble,
+                    Some((span, other)) => return Err(self.error(
+                        span,
+                        &format!("unknown noise pattern '{}', expected 'noise', 'turbulence' or 'marble'", other)
+                    )),
+                };
+                let scale = texture.scale.unwrap_or(1.0);
+                if scale <= 0.0 {
+                    return Err(fields.error("'scale' must be positive"))
+                }
+                let mut noise = NoiseTexture::new(pattern, scale);
+                noise.color = texture.color.map(vector).unwrap_or(noise.color);
+                Arc::new(noise)
+            },
+            other => return Err(self.error(
+                texture.kind.span(),
+                &format!("unknown texture type '{}', expected 'solid', 'checker', 'uv_checker', 'image' or 'noise'", other)
+            )),
+        };
+
+        Ok(texture)
+    }
+
+    fn material(
+        &self,
+        description: &Spanned<MaterialDescription>,
+        textures: &HashMap<&str, Arc<dyn Texture>>
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let material = description.get_ref();
+        let kind = material.kind.get_ref().as_str();
+
+        let color = |name: &str, color: Option<&ColorDescription>| -> Result<Arc<dyn Texture>, SceneError> {
+            fields.color(fields.require(name, color)?, textures)
+        };
+
+        let material: Arc<dyn Material> = match kind {
+            "lambertian" => {
+                fields.only(kind, &[("fuzz", material.fuzz.is_some()), ("refraction_index", material.refraction_index.is_some()), ("color", material.color.is_some()), ("anisotropy", material.anisotropy.is_some())])?;
+                Arc::new(Lambertian { albedo: color("albedo", material.albedo.as_ref())? })
+            },
+            "metal" => {
+                fields.only(kind, &[("refraction_index", material.refraction_index.is_some()), ("color", material.color.is_some()), ("anisotropy", material.anisotropy.is_some())])?;
+                let fuzz = material.fuzz.unwrap_or(0.0);
+                if !(0.0..=1.0).contains(&fuzz) {
+                    return Err(fields.error("'fuzz' must be between 0 and 1"))
+                }
+                Arc::new(Metal { albedo: color("albedo", material.albedo.as_ref())?, fuzz })
+            },
+            "dielectric" => {
+                fields.only(kind, &[("albedo", material.albedo.is_some()), ("fuzz", material.fuzz.is_some()), ("color", material.color.is_some()), ("anisotropy", material.anisotropy.is_some())])?;
+                let refraction_index = fields.require("refraction_index", material.refraction_index)?;
+                if refraction_index <= 0.0 {
+                    return Err(fields.error("'refraction_index' must be positive"))
+                }
+                Arc::new(Dielectric { refraction_index })
+            },
+            "light" => {
+                fields.only(kind, &[("albedo", material.albedo.is_some()), ("fuzz", material.fuzz.is_some()), ("refraction_index", material.refraction_index.is_some()), ("anisotropy", material.anisotropy.is_some())])?;
+                Arc::new(Light { color: color("color", material.color.as_ref())? })
+            },
+            "isotropic" => {
+                fields.only(kind, &[("fuzz", material.fuzz.is_some()), ("refraction_index", material.refraction_index.is_some()), ("color", material.color.is_some()), ("anisotropy", material.anisotropy.is_some())])?;
+                Arc::new(Isotropic { albedo: color("albedo", material.albedo.as_ref())? })
+            },
+            "henyey_greenstein" => {
+                fields.only(kind, &[("fuzz", material.fuzz.is_some()), ("refraction_index", material.refraction_index.is_some()), ("color", material.color.is_some())])?;
+                let anisotropy = fields.require("anisotropy", material.anisotropy)?;
+                if anisotropy <= -1.0 || anisotropy >= 1.0 {
+                    return Err(fields.error("'anisotropy' must be between -1 and 1"))
+                }
+                Arc::new(HenyeyGreenstein { albedo: color("albedo", material.albedo.as_ref())?, anisotropy })
+            },
+            other => return Err(self.error(
+                material.kind.span(),
+                &format!("unknown material type '{}', expected 'lambertian', 'metal', 'dielectric', 'light', 'isotropic' or 'henyey_greenstein'", other)
+            )),
+        };
+
+        Ok(material)
+    }
+
+    /// 'default_material' names the material of bodies that name none.
+    /// 'body' that must enclose an inside, which 'role' finds from its spans.
+    fn closed_body(
+        &self,
+        description: &Spanned<BodyDescription>,
+        default_material: Option<&Spanned<String>>,
+        materials: &HashMap<&str, Arc<dyn Material>>,
+        meshes: &mut HashMap<PathBuf, Arc<dyn Body>>,
+        role: &str,
+    ) -> Result<Arc<dyn Body>, SceneError> {
+        let kind = &description.get_ref().kind;
+
+        // Meshes may happen to be closed, but nothing checks that they are.
+        if let "triangle" | "quad" | "disk" | "obj" | "medium" | "volume" = kind.get_ref().as_str() {
+            return Err(self.error(kind.span(), &format!("'{}' bodies have no inside, so they cannot be {}", kind.get_ref(), role)))
+        }
+
+        self.body(description, default_material, materials, meshes)
+    }
+
+    fn body(
+        &self,
+        description: &Spanned<BodyDescription>,
+        default_material: Option<&Spanned<String>>,
+        materials: &HashMap<&str, Arc<dyn Material>>,
+        meshes: &mut HashMap<PathBuf, Arc<dyn Body>>,
+    ) -> Result<Arc<dyn Body>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let body = description.get_ref();
+        let kind = body.kind.get_ref().as_str();
+
+        let material = || -> Result<Arc<dyn Material>, SceneError> {
+            let name = fields.require("material", body.material.as_ref().or(default_material))?;
+
+            materials.get(name.get_ref().as_str())
+                .cloned()
+                .ok_or_else(|| self.error(name.span(), &format!("unknown material '{}'", name.get_ref())))
+        };
+
+        let radius = || -> Result<f32, SceneError> {
+            let radius = fields.require("radius", body.radius)?;
+            if radius <= 0.0 {
+                return Err(fields.error("'radius' must be positive"))
+            }
+            Ok(radius)
+        };
+
+        let height = || -> Result<f32, SceneError> {
+            let height = fields.require("height", body.height)?;
+            if height <= 0.0 {
+                return Err(fields.error("'height' must be positive"))
+            }
+            Ok(height)
+        };
+
+        let normal = || -> Result<Vec3D, SceneError> {
+            let normal = vector(fields.require("normal", body.normal)?);
+            if normal.is_near_zero() {
+                return Err(fields.error("'normal' must not be zero"))
+            }
+            Ok(normal.unit())
+        };
+
+        let shape: Arc<dyn Body> = match kind {
+            "sphere" => {
+                fields.only(kind, &body.unused(&["center", "radius", "material"]))?;
+                Arc::new(Sphere { center: vector(fields.require("center", body.center)?), radius: radius()?, material: material()? })
+            },
+            "plane" => {
+                fields.only(kind, &body.unused(&["center", "normal", "material"]))?;
+                Arc::new(Plane { center: vector(fields.require("center", body.center)?), normal: normal()?, material: material()? })
+            },
+            "triangle" => {
+                fields.only(kind, &body.unused(&["vertices", "material"]))?;
+                let [a, b, c] = fields.require("vertices", body.vertices)?.map(vector);
+                Arc::new(Triangle { a, b, c, material: material()? })
+            },
+            "quad" => {
+                fields.only(kind, &body.unused(&["corner", "edges", "material"]))?;
+                let [u, v] = fields.require("edges", body.edges)?.map(vector);
+                if u.cross(&v).is_near_zero() {
+                    return Err(fields.error("'edges' must not be parallel"))
+                }
+                Arc::new(Quad { corner: vector(fields.require("corner", body.corner)?), u, v, material: material()? })
+            },
+            "disk" => {
+                fields.only(kind, &body.unused(&["center", "normal", "radius", "material"]))?;
+                Arc::new(Disk { center: vector(fields.require("center", body.center)?), normal: normal()?, radius: radius()?, material: material()? })
+            },
+            "box" => {
+                fields.only(kind, &body.unused(&["corners", "material"]))?;
+                let [a, b] = fields.require("corners", body.corners)?.map(vector);
+                Arc::new(Cuboid::new(&a, &b, material()?))
+            },
+            "cylinder" => {
+                fields.only(kind, &body.unused(&["center", "radius", "height", "material"]))?;
+                Arc::new(Cylinder { center: vector(fields.require("center", body.center)?), radius: radius()?, height: height()?, material: material()? })
+            },
+            "cone" => {
+                fields.only(kind, &body.unused(&["center", "radius", "top_radius", "height", "material"]))?;
+                let top_radius = body.top_radius.unwrap_or(0.0);
+                if top_radius < 0.0 {
+                    return Err(fields.error("'top_radius' must not be negative"))
+                }
+                Arc::new(Cone { center: vector(fields.require("center", body.center)?), radius: radius()?, top_radius, height: height()?, material: material()? })
+            },
+            "torus" => {
+                fields.only(kind, &body.unused(&["center", "radius", "tube_radius", "material"]))?;
+                let tube_radius = fields.require("tube_radius", body.tube_radius)?;
+                if tube_radius <= 0.0 {
+                    return Err(fields.error("'tube_radius' must be positive"))
+                }
+                Arc::new(Torus { center: vector(fields.require("center", body.center)?), radius: radius()?, tube_radius, material: material()? })
+            },
+            "capsule" => {
+                fields.only(kind, &body.unused(&["center", "radius", "height", "material"]))?;
+                let height = fields.require("height", body.height)?;
+                if height < 0.0 {
+                    return Err(fields.error("'height' must not be negative"))
+                }
+                Arc::new(Capsule { center: vector(fields.require("center", body.center)?), radius: radius()?, height, material: material()? })
+            },
+            "union" | "intersection" | "difference" => {
+                fields.only(kind, &body.unused(&["operands"]))?;
+                let operands = fields.require("operands", body.operands.as_ref())?;
+                if operands.len() < 2 {
+                    return Err(fields.error("'operands' needs at least two bodies"))
+                }
+
+                let operation = match kind {
+                    "union" => CsgOperation::Union,
+                    "intersection" => CsgOperation::Intersection,
+                    _ => CsgOperation::Difference,
+                };
+
+                // Later operands are combined with everything before them, so a
+                // difference takes all of them away from the first.
+                let mut operands = operands.iter().map(|operand| self.closed_body(operand, None, materials, meshes, "CSG operands"));
+                let first = operands.next().unwrap()?;
+                operands.try_fold(first, |left, right| -> Result<Arc<dyn Body>, SceneError> {
+                    Ok(Arc::new(Csg::new(operation, left, right?)))
+                })?
+            },
+            "sdf" => {
+                fields.only(kind, &body.unused(&["sdf", "max_steps", "epsilon", "material"]))?;
+                let mut sdf_body = SdfBody::new(self.sdf(fields.require("sdf", body.sdf.as_ref())?)?, material()?);
+
+                if let Some(max_steps) = body.max_steps {
+                    if max_steps == 0 {
+                        return Err(fields.error("'max_steps' must be positive"))
+                    }
+                    sdf_body = sdf_body.with_max_steps(max_steps);
+                }
+
+                if let Some(epsilon) = body.epsilon {
+                    if epsilon <= 0.0 {
+                        return Err(fields.error("'epsilon' must be positive"))
+                    }
+                    sdf_body = sdf_body.with_epsilon(epsilon);
+                }
+
+                Arc::new(sdf_body)
+            },
+            "medium" => {
+                fields.only(kind, &body.unused(&["boundary", "density", "material"]))?;
+                let density = fields.require("density", body.density)?;
+                if density <= 0.0 {
+                    return Err(fields.error("'density' must be positive"))
+                }
+                // The boundary is never seen, so it needs no material of its own.
+                let boundary = self.closed_body(fields.require("boundary", body.boundary.as_deref())?, body.material.as_ref(), materials, meshes, "medium boundaries")?;
+                Arc::new(ConstantMedium { boundary, density, phase: material()? })
+            },
+            "volume" => {
+                fields.only(kind, &body.unused(&["path", "corners", "density", "emission", "emission_path", "material"]))?;
+                let [a, b] = fields.require("corners", body.corners)?.map(vector);
+                if a.x == b.x || a.y == b.y || a.z == b.z {
+                    return Err(fields.error("'corners' must span a box that is not flat"))
+                }
+                let density = body.density.unwrap_or(1.0);
+                if density < 0.0 {
+                    return Err(fields.error("'density' must not be negative"))
+                }
+
+                let directory = self.path.parent().unwrap_or(Path::new(""));
+                let load = |name: &str, path: Option<&String>| -> Result<Arc<VoxelGrid>, SceneError> {
+                    let path = directory.join(fields.require(name, path)?);
+                    VoxelGrid::load(&path).map(Arc::new).map_err(|error| SceneError::Io { path, error })
+                };
+
+                let grid = load("path", body.path.as_ref())?;
+                let mut volume = GridVolume::new(grid.clone(), &a, &b, density, material()?);
+
+                if let Some(color) = body.emission {
+                    // Fire glows where it is dense unless its temperature is given separately.
+                    let emission = match body.emission_path {
+                        Some(_) => load("emission_path", body.emission_path.as_ref())?,
+                        None => grid,
+                    };
+                    volume = volume.with_emission(emission, vector(color));
+                } else if body.emission_path.is_some() {
+                    return Err(fields.error("'emission_path' needs an 'emission' color"))
+                }
+
+                Arc::new(volume)
+            },
+            "obj" => {
+                fields.only(kind, &body.unused(&["path"]))?;
+                let path = self.path.parent().unwrap_or(Path::new("")).join(fields.require("path", body.path.as_ref())?);
+                // Every body loading the same file shares one copy of the mesh.
+                match meshes.get(&path) {
+                    Some(mesh) => mesh.clone(),
+                    None => {
+                        let mesh: Arc<dyn Body> = Arc::new(obj::load_obj(&path).map_err(SceneError::Obj)?);
+                        meshes.insert(path, mesh.clone());
+                        mesh
+                    },
+                }
+            },
+            other => return Err(self.error(
+                body.kind.span(),
+                &format!("unknown body type '{}', expected 'sphere', 'plane', 'triangle', 'quad', 'disk', 'box', 'cylinder', 'cone', 'torus', 'capsule', 'union', 'intersection', 'difference', 'sdf', 'medium', 'volume' or 'obj'", other)
+            )),
+        };
+
+        let shape: Arc<dyn Body> = match self.transform(&fields, body)? {
+            Some(transform) => Arc::new(Instance::new(shape, transform)),
+            None => shape,
+        };
+
+        let Some(motion) = &body.motion else {
+            return Ok(shape)
+        };
+        if motion.len() < 2 {
+            return Err(fields.error("'motion' needs at least two keyframes"))
+        }
+
+        let keyframes = motion.iter()
+            .map(|description| self.keyframe(description))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Arc::new(Motion::new(shape, keyframes)))
+    }
+
+    fn keyframe(&self, description: &Spanned<KeyframeDescription>) -> Result<Keyframe, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let keyframe = description.get_ref();
+
+        let mut result = Keyframe::new(fields.require("time", keyframe.time)?);
+
+        if let Some(scale) = &keyframe.scale {
+            result = result.with_scale(self.scale(&fields, scale)?);
+        }
+        if let Some(rotation) = keyframe.rotate {
+            result = result.with_rotation(vector(rotation));
+        }
+        if let Some(translation) = keyframe.translate {
+            result = result.with_translation(vector(translation));
+        }
+
+        Ok(result)
+    }
+
+    fn scale(&self, fields: &Fields, scale: &ScaleDescription) -> Result<Vec3D, SceneError> {
+        let factors = match *scale {
+            ScaleDescription::Uniform(factor) => Vec3D::one() * factor,
+            ScaleDescription::Axes(factors) => vector(factors),
+        };
+        if factors.x == 0.0 || factors.y == 0.0 || factors.z == 0.0 {
+            return Err(fields.error("'scale' must not be zero"))
+        }
+
+        Ok(factors)
+    }
+
+    fn fog(
+        &self,
+        description: &Spanned<FogDescription>,
+        materials: &HashMap<&str, Arc<dyn Material>>
+    ) -> Result<(f32, Arc<dyn Material>), SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let fog = description.get_ref();
+
+        let density = fields.require("density", fog.density)?;
+        if density <= 0.0 {
+            return Err(fields.error("'density' must be positive"))
+        }
+
+        let name = fields.require("material", fog.material.as_ref())?;
+        let phase = materials.get(name.get_ref().as_str())
+            .cloned()
+            .ok_or_else(|| self.error(name.span(), &format!("unknown material '{}'", name.get_ref())))?;
+
+        Ok((density, phase))
+    }
+
+    fn sdf(&self, description: &Spanned<SdfDescription>) -> Result<Arc<dyn Sdf>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let sdf = description.get_ref();
+        let kind = sdf.kind.get_ref().as_str();
+
+        let center = || fields.require("center", sdf.center).map(vector);
+
+        let radius = || -> Result<f32, SceneError> {
+            let radius = fields.require("radius", sdf.radius)?;
+            if radius <= 0.0 {
+                return Err(fields.error("'radius' must be positive"))
+            }
+            Ok(radius)
+        };
+
+        let inner = || self.sdf(fields.require("sdf", sdf.sdf.as_deref())?);
+
+        let shape: Arc<dyn Sdf> = match kind {
+            "sphere" => {
+                fields.only(kind, &sdf.unused(&["center", "radius"]))?;
+                Arc::new(SphereSdf { center: center()?, radius: radius()? })
+            },
+            "rounded_box" => {
+                fields.only(kind, &sdf.unused(&["center", "size", "rounding"]))?;
+                let size = vector(fields.require("size", sdf.size)?);
+                if size.x <= 0.0 || size.y <= 0.0 || size.z <= 0.0 {
+                    return Err(fields.error("'size' must be positive"))
+                }
+                let rounding = sdf.rounding.unwrap_or(0.0);
+                if rounding < 0.0 || 2.0 * rounding > size.x.min(size.y).min(size.z) {
+                    return Err(fields.error("'rounding' must be between 0 and half the smallest side"))
+                }
+                Arc::new(RoundedBoxSdf { center: center()?, size, rounding })
+            },
+            "torus" => {
+                fields.only(kind, &sdf.unused(&["center", "radius", "tube_radius"]))?;
+                let tube_radius = fields.require("tube_radius", sdf.tube_radius)?;
+                if tube_radius <= 0.0 {
+                    return Err(fields.error("'tube_radius' must be positive"))
+                }
+                Arc::new(TorusSdf { center: center()?, radius: radius()?, tube_radius })
+            },
+            "capsule" => {
+                fields.only(kind, &sdf.unused(&["center", "radius", "height"]))?;
+                let height = fields.require("height", sdf.height)?;
+                if height < 0.0 {
+                    return Err(fields.error("'height' must not be negative"))
+                }
+                Arc::new(CapsuleSdf { center: center()?, radius: radius()?, height })
+            },
+            "mandelbulb" => {
+                fields.only(kind, &sdf.unused(&["center", "radius", "power", "iterations"]))?;
+                let power = sdf.power.unwrap_or(8.0);
+                if power < 2.0 {
+                    return Err(fields.error("'power' must be at least 2"))
+                }
+                Arc::new(Mandelbulb { center: center()?, radius: radius()?, power, iterations: sdf.iterations.unwrap_or(12) })
+            },
+            "smooth_union" => {
+                fields.only(kind, &sdf.unused(&["operands", "smoothness"]))?;
+                let operands = fields.require("operands", sdf.operands.as_ref())?;
+                if operands.len() < 2 {
+                    return Err(fields.error("'operands' needs at least two fields"))
+                }
+                let smoothness = fields.require("smoothness", sdf.smoothness)?;
+                if smoothness < 0.0 {
+                    return Err(fields.error("'smoothness' must not be negative"))
+                }
+
+                let mut operands = operands.iter().map(|operand| self.sdf(operand));
+                let first = operands.next().unwrap()?;
+                operands.try_fold(first, |a, b| -> Result<Arc<dyn Sdf>, SceneError> {
+                    Ok(Arc::new(SmoothUnion { a, b: b?, smoothness }))
+                })?
+            },
+            "twist" => {
+                fields.only(kind, &sdf.unused(&["sdf", "rate"]))?;
+                Arc::new(Twist::new(inner()?, fields.require("rate", sdf.rate)?))
+            },
+            "repeat" => {
+                fields.only(kind, &sdf.unused(&["sdf", "period", "count"]))?;
+                let period = vector(fields.require("period", sdf.period)?);
+                if period.x <= 0.0 || period.y <= 0.0 || period.z <= 0.0 {
+                    return Err(fields.error("'period' must be positive"))
+                }
+                let count = fields.require("count", sdf.count)?;
+                if count.contains(&0) {
+                    return Err(fields.error("'count' must be positive"))
+                }
+                Arc::new(Repetition::new(inner()?, period, count))
+            },
+            other => return Err(self.error(
+                sdf.kind.span(),
+                &format!("unknown sdf type '{}', expected 'sphere', 'rounded_box', 'torus', 'capsule', 'mandelbulb', 'smooth_union', 'twist' or 'repeat'", other)
+            )),
+        };
+
+        Ok(shape)
+    }
+
+    /// Scales, then rotates around x, y and z in turn, then translates. 'None'
+    /// when the body sets none of them.
+    fn transform(&self, fields: &Fields, body: &BodyDescription) -> Result<Option<Transform>, SceneError> {
+        if body.translate.is_none() && body.rotate.is_none() && body.scale.is_none() {
+            return Ok(None)
+        }
+
+        let mut transform = Transform::identity();
+
+        if let Some(scale) = &body.scale {
+            transform = transform.then(&Transform::scaling(&self.scale(fields, scale)?));
+        }
+
+        if let Some([x, y, z]) = body.rotate {
+            for (axis, degrees) in [(Vec3D::x_unit(), x), (Vec3D::y_unit(), y), (Vec3D::z_unit(), z)] {
+                transform = transform.then(&Transform::rotation(&axis, degrees));
+            }
+        }
+
+        if let Some(offset) = body.translate {
+            transform = transform.then(&Transform::translation(&vector(offset)));
+        }
+
+        Ok(Some(transform))
+    }
+
+    fn environment(&self, description: &Spanned<EnvironmentDescription>) -> Result<Arc<dyn Environment>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let environment = description.get_ref();
+        let kind = environment.kind.get_ref().as_str();
+
+        let intensity = || -> Result<f32, SceneError> {
+            let intensity = environment.intensity.unwrap_or(1.0);
+            if intensity < 0.0 {
+                return Err(fields.error("'intensity' must not be negative"))
+            }
+            Ok(intensity)
+        };
+
+        let environment: Arc<dyn Environment> = match kind {
+            "uniform" => {
+                fields.only(kind, &[("path", environment.path.is_some()), ("rotation", environment.rotation.is_some()), ("intensity", environment.intensity.is_some()), ("sun_direction", environment.sun_direction.is_some()), ("turbidity", environment.turbidity.is_some())])?;
+                Arc::new(UniformEnvironment::new(vector(fields.require("color", environment.color)?)))
+            },
+            "map" => {
+                fields.only(kind, &[("color", environment.color.is_some()), ("sun_direction", environment.sun_direction.is_some()), ("turbidity", environment.turbidity.is_some())])?;
+                let intensity = intensity()?;
+                let path = self.path.parent().unwrap_or(Path::new("")).join(fields.require("path", environment.path.as_ref())?);
+                let map = EnvironmentMap::load(&path).map_err(|error| SceneError::Io { path, error })?;
+                Arc::new(map.with_rotation(environment.rotation.unwrap_or(0.0)).with_intensity(intensity))
+            },
+            "sky" => {
+                fields.only(kind, &[("color", environment.color.is_some()), ("path", environment.path.is_some()), ("rotation", environment.rotation.is_some())])?;
+                let sun_direction = vector(fields.require("sun_direction", environment.sun_direction)?);
+                if sun_direction.is_near_zero() {
+                    return Err(fields.error("'sun_direction' must not be zero"))
+                }
+                let turbidity = environment.turbidity.unwrap_or(3.0);
+                if !(1.0..=20.0).contains(&turbidity) {
+                    return Err(fields.error("'turbidity' must be between 1 and 20"))
+                }
+                Arc::new(Sky::new(sun_direction, turbidity).with_intensity(intensity()?))
+            },
+            other => return Err(self.error(
+                environment.kind.span(),
+                &format!("unknown environment type '{}', expected 'uniform', 'map' or 'sky'", other)
+            )),
+        };
+
+        Ok(environment)
+    }
+
+    fn light_source(&self, description: &Spanned<LightDescription>) -> Result<Arc<dyn LightSource>, SceneError> {
+        let fields = Fields { loader: self, span: description.span() };
+        let light = description.get_ref();
+        let kind = light.kind.get_ref().as_str();
+
+        let direction = || -> Result<Vec3D, SceneError> {
+            let direction = vector(fields.require("direction", light.direction)?);
+            if direction.is_near_zero() {
+                return Err(fields.error("'direction' must not be zero"))
+            }
+            Ok(direction)
+        };
+
+        let light: Arc<dyn LightSource> = match kind {
+            "point" => {
+                fields.only(kind, &[("direction", light.direction.is_some()), ("inner_angle", light.inner_angle.is_some()), ("outer_angle", light.outer_angle.is_some()), ("angular_diameter", light.angular_diameter.is_some())])?;
+                Arc::new(PointLight { position: vector(fields.require("position", light.position)?), intensity: vector(fields.require("intensity", light.intensity)?) })
+            },
+            "spot" => {
+                fields.only(kind, &[("angular_diameter", light.angular_diameter.is_some())])?;
+                let outer_angle = fields.require("outer_angle", light.outer_angle)?;
+                let inner_angle = light.inner_angle.unwrap_or(outer_angle);
+                if !(0.0..=outer_angle).contains(&inner_angle) || outer_angle > 180.0 {
+                    return Err(fields.error("'inner_angle' and 'outer_angle' must satisfy 0 <= inner_angle <= outer_angle <= 180"))
+                }
+                Arc::new(SpotLight::new(
+                    vector(fields.require("position", light.position)?),
+                    direction()?,
+                    vector(fields.require("intensity", light.intensity)?),
+                    inner_angle,
+                    outer_angle,
+                ))
+            },
+            "directional" => {
+                fields.only(kind, &[("position", light.position.is_some()), ("inner_angle", light.inner_angle.is_some()), ("outer_angle", light.outer_angle.is_some())])?;
+                let angular_diameter = light.angular_diameter.unwrap_or(0.0);
+                if !(0.0..180.0).contains(&angular_diameter) {
+                    return Err(fields.error("'angular_diameter' must be between 0 and 180 degrees"))
+                }
+                Arc::new(DirectionalLight::new(direction()?, vector(fields.require("intensity", light.intensity)?), angular_diameter))
+            },
+            other => return Err(self.error(
+                light.kind.span(),
+                &format!("unknown light type '{}', expected 'point', 'spot' or 'directional'", other)
+            )),
+        };
+
+        Ok(light)
+    }
+}
+
+/// Validation of the optional fields shared by every kind of material or body.
+struct Fields<'a> {
+    loader: &'a SceneLoader<'a>,
+    span: Range<usize>,
+}
+
+impl Fields<'_> {
+    fn error(&self, message: &str) -> SceneError {
+        self.loader.error(self.span.clone(), message)
+    }
+
+    fn require<T>(&self, name: &str, value: Option<T>) -> Result<T, SceneError> {
+        value.ok_or_else(|| self.error(&format!("missing field '{}'", name)))
+    }
+
+    /// A constant color, or the name of a texture defined earlier.
+    fn color(&self, color: &ColorDescription, textures: &HashMap<&str, Arc<dyn Texture>>) -> Result<Arc<dyn Texture>, SceneError> {
+        match color {
+            ColorDescription::Constant(color) => Ok(Arc::new(SolidColor::new(vector(*color)))),
+            ColorDescription::Texture(name) => textures.get(name.as_str())
+                .cloned()
+                .ok_or_else(|| self.error(&format!("unknown texture '{}'", name))),
+        }
+    }
+
+    /// Fails on the first field in 'unused' that is set.
+    fn only(&self, kind: &str, unused: &[(&str, bool)]) -> Result<(), SceneError> {
+        match unused.iter().find(|(_, set)| *set) {
+            Some((name, _)) => Err(self.error(&format!("field '{}' does not apply to '{}'", name, kind))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn vector([x, y, z]: [f32; 3]) -> Vec3D {
+    Vec3D::new(x, y, z)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: Option<Spanned<CameraDescription>>,
+    render: Option<Spanned<RenderDescription>>,
+    environment: Option<Spanned<EnvironmentDescription>>,
+    fog: Option<Spanned<FogDescription>>,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDescription>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDescription>>,
+    #[serde(default)]
+    bodies: Vec<Spanned<BodyDescription>>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDescription>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    aspect_ratio: Option<f32>,
+    image_width: Option<usize>,
+    vertical_field_of_view: Option<f32>,
+    look_from: Option<[f32; 3]>,
+    look_to: Option<[f32; 3]>,
+    view_up: Option<[f32; 3]>,
+    defocus_angle: Option<f32>,
+    focus_distance: Option<f32>,
+    image_scaling: Option<f32>,
+    shutter_open: Option<f32>,
+    shutter_close: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+    samples_per_pixel: Option<usize>,
+    min_bounces: Option<usize>,
+    max_bounces: Option<usize>,
+    acceleration: Option<Spanned<String>>,
+    exposure: Option<f32>,
+    tone_map: Option<Spanned<String>>,
+    white_point: Option<f32>,
+    srgb: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    color: Option<[f32; 3]>,
+    path: Option<String>,
+    rotation: Option<f32>,
+    intensity: Option<f32>,
+    sun_direction: Option<[f32; 3]>,
+    turbidity: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDescription {
+    Constant([f32; 3]),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    color: Option<[f32; 3]>,
+    scale: Option<f32>,
+    even: Option<ColorDescription>,
+    odd: Option<ColorDescription>,
+    columns: Option<f32>,
+    rows: Option<f32>,
+    path: Option<String>,
+    wrap: Option<Spanned<String>>,
+    pattern: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    albedo: Option<ColorDescription>,
+    fuzz: Option<f32>,
+    refraction_index: Option<f32>,
+    color: Option<ColorDescription>,
+    anisotropy: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BodyDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    material: Option<Spanned<String>>,
+    center: Option<[f32; 3]>,
+    radius: Option<f32>,
+    normal: Option<[f32; 3]>,
+    vertices: Option<[[f32; 3]; 3]>,
+    corner: Option<[f32; 3]>,
+    edges: Option<[[f32; 3]; 2]>,
+    corners: Option<[[f32; 3]; 2]>,
+    height: Option<f32>,
+    top_radius: Option<f32>,
+    tube_radius: Option<f32>,
+    /// Bodies combined by constructive solid geometry.
+    operands: Option<Vec<Spanned<BodyDescription>>>,
+    sdf: Option<Spanned<SdfDescription>>,
+    max_steps: Option<usize>,
+    epsilon: Option<f32>,
+    /// Closed body a medium fills.
+    boundary: Option<Box<Spanned<BodyDescription>>>,
+    density: Option<f32>,
+    /// Color a volume glows with, scaled by its emission grid.
+    emission: Option<[f32; 3]>,
+    emission_path: Option<String>,
+    path: Option<String>,
+    translate: Option<[f32; 3]>,
+    /// Degrees around x, y and z.
+    rotate: Option<[f32; 3]>,
+    scale: Option<ScaleDescription>,
+    /// Keyframes a body moves through after its transform.
+    motion: Option<Vec<Spanned<KeyframeDescription>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDescription {
+    time: Option<f32>,
+    translate: Option<[f32; 3]>,
+    rotate: Option<[f32; 3]>,
+    scale: Option<ScaleDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDescription {
+    Uniform(f32),
+    Axes([f32; 3]),
+}
+
+impl BodyDescription {
+    /// The shape fields that are set, except those in 'used', for 'Fields::only'.
+    /// Transforms and motion apply to every body.
+    fn unused(&self, used: &[&str]) -> Vec<(&'static str, bool)> {
+        [
+            ("material", self.material.is_some()),
+            ("center", self.center.is_some()),
+            ("radius", self.radius.is_some()),
+            ("normal", self.normal.is_some()),
+            ("vertices", self.vertices.is_some()),
+            ("corner", self.corner.is_some()),
+            ("edges", self.edges.is_some()),
+            ("corners", self.corners.is_some()),
+            ("height", self.height.is_some()),
+            ("top_radius", self.top_radius.is_some()),
+            ("tube_radius", self.tube_radius.is_some()),
+            ("operands", self.operands.is_some()),
+            ("sdf", self.sdf.is_some()),
+            ("max_steps", self.max_steps.is_some()),
+            ("epsilon", self.epsilon.is_some()),
+            ("boundary", self.boundary.is_some()),
+            ("density", self.density.is_some()),
+            ("emission", self.emission.is_some()),
+            ("emission_path", self.emission_path.is_some()),
+            ("path", self.path.is_some()),
+        ].into_iter().filter(|(name, _)| !used.contains(name)).collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FogDescription {
+    density: Option<f32>,
+    /// Phase function, like an 'isotropic' material.
+    material: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    center: Option<[f32; 3]>,
+    radius: Option<f32>,
+    size: Option<[f32; 3]>,
+    rounding: Option<f32>,
+    tube_radius: Option<f32>,
+    height: Option<f32>,
+    power: Option<f32>,
+    iterations: Option<usize>,
+    smoothness: Option<f32>,
+    operands: Option<Vec<Spanned<SdfDescription>>>,
+    /// The field a combinator changes.
+    sdf: Option<Box<Spanned<SdfDescription>>>,
+    /// Degrees per unit of height.
+    rate: Option<f32>,
+    period: Option<[f32; 3]>,
+    count: Option<[usize; 3]>,
+}
+
+impl SdfDescription {
+    /// The fields that are set, except those in 'used', for 'Fields::only'.
+    fn unused(&self, used: &[&str]) -> Vec<(&'static str, bool)> {
+        [
+            ("center", self.center.is_some()),
+            ("radius", self.radius.is_some()),
+            ("size", self.size.is_some()),
+            ("rounding", self.rounding.is_some()),
+            ("tube_radius", self.tube_radius.is_some()),
+            ("height", self.height.is_some()),
+            ("power", self.power.is_some()),
+            ("iterations", self.iterations.is_some()),
+            ("smoothness", self.smoothness.is_some()),
+            ("operands", self.operands.is_some()),
+            ("sdf", self.sdf.is_some()),
+            ("rate", self.rate.is_some()),
+            ("period", self.period.is_some()),
+            ("count", self.count.is_some()),
+        ].into_iter().filter(|(name, _)| !used.contains(name)).collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    position: Option<[f32; 3]>,
+    direction: Option<[f32; 3]>,
+    intensity: Option<[f32; 3]>,
+    inner_angle: Option<f32>,
+    outer_angle: Option<f32>,
+    angular_diameter: Option<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(source: &str) -> Result<Scene, SceneError> {
+        SceneLoader { path: Path::new("test.toml"), source }.load()
+    }
+
+    fn error(source: &str) -> String {
+        match load(source) {
+            Ok(_) => panic!("scene should not load"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    const MATERIAL: &str = "[materials.red]\ntype = \"lambertian\"\nalbedo = [0.8, 0.2, 0.2]\n";
+
+    #[test]
+    fn loads_bodies_in_order() {
+        let source = format!("{}\n[[bodies]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"\n\n[[bodies]]\ntype = \"plane\"\ncenter = [0, -1, 0]\nnormal = [0, 1, 0]\nmaterial = \"red\"\n", MATERIAL);
+        let scene = load(&source).unwrap_or_else(|error| panic!("{}", error));
+
+        assert_eq!(scene.world.len(), 2);
+        assert!(scene.environment.is_none() && scene.fog.is_none());
+    }
+
+    #[test]
+    fn loads_the_example_scenes() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                load_scene(&path).unwrap_or_else(|error| panic!("{}", error));
+            }
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors_where_they_are() {
+        assert!(error("[camera]\nimage_width = \n").starts_with("test.toml:2:"));
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let message = error("[camera]\nimage_widht = 100\n");
+
+        assert!(message.starts_with("test.toml:2:1: unknown field `image_widht`"), "{}", message);
+    }
+
+    #[test]
+    fn reports_missing_fields_at_their_table() {
+        let message = error(&format!("{}\n[[bodies]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nmaterial = \"red\"\n", MATERIAL));
+
+        assert_eq!(message, "test.toml:5:1: missing field 'radius'");
+    }
+
+    #[test]
+    fn reports_unknown_names_where_they_are_used() {
+        let message = error("[[bodies]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"blue\"\n");
+        assert_eq!(message, "test.toml:5:12: unknown material 'blue'");
+
+        let message = error("[[bodies]]\ntype = \"sphear\"\n");
+        assert!(message.starts_with("test.toml:2:8: unknown body type 'sphear', expected 'sphere'"), "{}", message);
+    }
+
+    #[test]
+    fn reports_fields_of_other_body_types() {
+        let message = error(&format!("{}\n[[bodies]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nnormal = [0, 1, 0]\nmaterial = \"red\"\n", MATERIAL));
+
+        assert_eq!(message, "test.toml:5:1: field 'normal' does not apply to 'sphere'");
+    }
+
+    #[test]
+    fn reports_invalid_values() {
+        let message = error(&format!("{}\n[[bodies]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = -1\nmaterial = \"red\"\n", MATERIAL));
+        assert_eq!(message, "test.toml:5:1: 'radius' must be positive");
+
+        let message = error("[render]\nmin_bounces = 5\nmax_bounces = 2\n");
+        assert!(message.ends_with("'min_bounces' must not exceed 'max_bounces'"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_open_csg_operands() {
+        let source = format!("{}\n[[bodies]]\ntype = \"difference\"\noperands = [\n  {{ type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }},\n  {{ type = \"triangle\", vertices = [[0, 0, 0], [1, 0, 0], [0, 1, 0]], material = \"red\" }},\n]\n", MATERIAL);
+        let message = error(&source);
+
+        assert_eq!(message, "test.toml:9:12: 'triangle' bodies have no inside, so they cannot be CSG operands");
+    }
+
+    #[test]
+    fn rejects_open_medium_boundaries() {
+        let source = format!("{}\n[[bodies]]\ntype = \"medium\"\ndensity = 1\nmaterial = \"red\"\nboundary = {{ type = \"disk\", center = [0, 0, 0], normal = [0, 1, 0], radius = 1 }}\n", MATERIAL);
+        let message = error(&source);
+
+        assert_eq!(message, "test.toml:9:21: 'disk' bodies have no inside, so they cannot be medium boundaries");
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let error = load_scene("no/such/scene.toml").err().unwrap();
+
+        assert!(matches!(error, SceneError::Io { .. }));
+    }
+}
@@ -0,0 +1,14 @@
+pub mod primitives;
+pub mod combinators;
+
+use crate::{vector::Vec3D, aabb::Aabb};
+
+/// Signed distance field: how far a point is from the closest surface,
+/// negative inside. Sphere tracing steps by this distance, so it may
+/// underestimate but must never overestimate.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, point: &Vec3D) -> f32;
+
+    /// Box outside of which the distance is never negative.
+    fn bounding_box(&self) -> Aabb;
+}
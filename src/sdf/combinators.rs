@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use crate::{vector::Vec3D, aabb::Aabb, interval::Interval, degrees_to_radians};
+
+use super::Sdf;
+
+/// Union of two fields that blends them together within 'smoothness' of
+/// where they meet. A 'smoothness' of zero is a plain union.
+pub struct SmoothUnion {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+    pub smoothness: f32,
+}
+
+impl Sdf for SmoothUnion {
+    /// Quilez's polynomial smooth minimum.
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let (a, b) = (self.a.distance(point), self.b.distance(point));
+        if self.smoothness <= 0.0 {
+            return a.min(b)
+        }
+
+        let h = (self.smoothness - (a - b).abs()).max(0.0) / self.smoothness;
+
+        a.min(b) - h.powi(2) * self.smoothness / 4.0
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // The blend can swell past both fields by a quarter of 'smoothness' on
+        // each side, and 'expand' splits its padding between the two.
+        let padding = self.smoothness / 2.0;
+        let Aabb { x, y, z } = Aabb::enclosing(&self.a.bounding_box(), &self.b.bounding_box());
+
+        Aabb::new(x.expand(padding), y.expand(padding), z.expand(padding))
+    }
+}
+
+/// Twists a field around the vertical axis through the origin by 'rate'
+/// degrees per unit of height.
+pub struct Twist {
+    pub sdf: Arc<dyn Sdf>,
+    pub rate: f32,
+    /// How much twisting can shorten distances, which the steps are scaled down by.
+    lipschitz: f32,
+    radius: f32,
+}
+
+impl Twist {
+    pub fn new(sdf: Arc<dyn Sdf>, rate: f32) -> Self {
+        // The field swept around the axis stays within its furthest corner.
+        let Aabb { x, z, .. } = sdf.bounding_box();
+        let radius = (x.min.abs().max(x.max.abs()).powi(2) + z.min.abs().max(z.max.abs()).powi(2)).sqrt();
+        let lipschitz = (1.0 + (degrees_to_radians(rate) * radius).powi(2)).sqrt();
+
+        Self { sdf, rate, lipschitz, radius }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let (sin, cos) = degrees_to_radians(-self.rate * point.y).sin_cos();
+        let untwisted = Vec3D::new(cos * point.x - sin * point.z, point.y, sin * point.x + cos * point.z);
+
+        self.sdf.distance(&untwisted) / self.lipschitz
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let y = self.sdf.bounding_box().y;
+        Aabb::new(Interval::new(-self.radius, self.radius), y, Interval::new(-self.radius, self.radius))
+    }
+}
+
+/// 'count' copies of a field along each axis, 'period' apart and centered on
+/// the original. Exact as long as the field fits within one period.
+pub struct Repetition {
+    sdf: Arc<dyn Sdf>,
+    period: Vec3D,
+    count: [usize; 3],
+}
+
+impl Repetition {
+    /// Panics if any 'count' is zero.
+    pub fn new(sdf: Arc<dyn Sdf>, period: Vec3D, count: [usize; 3]) -> Self {
+        assert!(!count.contains(&0), "repetition count must be positive");
+
+        Self { sdf, period, count }
+    }
+}
+
+impl Sdf for Repetition {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        // Folds 'point' into the closest copy.
+        let fold = |p: f32, period: f32, count: usize| -> f32 {
+            let offset = (count as f32 - 1.0) / 2.0;
+            let index = (p / period + offset).round().clamp(0.0, count as f32 - 1.0);
+            p - period * (index - offset)
+        };
+
+        let [x, y, z] = self.count;
+        let folded = Vec3D::new(fold(point.x, self.period.x, x), fold(point.y, self.period.y, y), fold(point.z, self.period.z, z));
+
+        self.sdf.distance(&folded)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let Aabb { x, y, z } = self.sdf.bounding_box();
+        let grow = |interval: Interval, period: f32, count: usize| {
+            let reach = period * (count as f32 - 1.0) / 2.0;
+            Interval::new(interval.min - reach, interval.max + reach)
+        };
+
+        Aabb::new(grow(x, self.period.x, self.count[0]), grow(y, self.period.y, self.count[1]), grow(z, self.period.z, self.count[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdf::primitives::{SphereSdf, RoundedBoxSdf};
+
+    fn sphere(x: f32) -> Arc<dyn Sdf> {
+        Arc::new(SphereSdf { center: Vec3D::new(x, 0.0, 0.0), radius: 1.0 })
+    }
+
+    fn contains(bounding_box: &Aabb, point: &Vec3D) -> bool {
+        bounding_box.x.contains(point.x) && bounding_box.y.contains(point.y) && bounding_box.z.contains(point.z)
+    }
+
+    #[test]
+    fn smooth_union_bounds_contain_the_blend() {
+        // Where the fields are equal the blend swells by the full quarter of 'smoothness'.
+        let union = SmoothUnion { a: sphere(-0.1), b: sphere(0.1), smoothness: 2.0 };
+
+        // Above both spheres and their boxes, by more than an eighth of 'smoothness'.
+        let point = Vec3D::new(0.0, 1.4, 0.0);
+        assert!(!contains(&union.a.bounding_box(), &point) && !contains(&union.b.bounding_box(), &point));
+        assert!(union.distance(&point) < 0.0);
+        assert!(contains(&union.bounding_box(), &point));
+    }
+
+    #[test]
+    fn smooth_union_without_smoothness_is_a_union() {
+        let union = SmoothUnion { a: sphere(-1.5), b: sphere(1.5), smoothness: 0.0 };
+
+        for point in [Vec3D::zero(), Vec3D::new(-1.5, 0.5, 0.0), Vec3D::new(3.0, 2.0, -1.0)] {
+            assert_eq!(union.distance(&point), union.a.distance(&point).min(union.b.distance(&point)));
+        }
+    }
+
+    #[test]
+    fn twist_rotates_the_field_with_height() {
+        let bar: Arc<dyn Sdf> = Arc::new(RoundedBoxSdf { center: Vec3D::zero(), size: Vec3D::new(2.0, 4.0, 0.5), rounding: 0.0 });
+        let twist = Twist::new(bar.clone(), 45.0);
+
+        // Rotating a point by 45 degrees per unit of height around the y axis
+        // moves it to where the twisted field has the sign it had before.
+        for point in [Vec3D::new(0.9, 1.0, 0.0), Vec3D::new(0.5, -1.5, 0.2), Vec3D::new(1.2, 0.5, 0.0), Vec3D::new(0.0, 1.0, 0.5)] {
+            let (sin, cos) = degrees_to_radians(45.0 * point.y).sin_cos();
+            let twisted = Vec3D::new(cos * point.x - sin * point.z, point.y, sin * point.x + cos * point.z);
+
+            assert_eq!(twist.distance(&twisted) < 0.0, bar.distance(&point) < 0.0, "{:?}", point);
+            assert!(twist.distance(&twisted).abs() <= bar.distance(&point).abs() + 1e-5);
+
+            let bounding_box = twist.bounding_box();
+            if bar.distance(&point) < 0.0 {
+                assert!(bounding_box.x.contains(twisted.x) && bounding_box.z.contains(twisted.z));
+            }
+        }
+    }
+
+    #[test]
+    fn repetition_copies_match_the_original() {
+        let original = sphere(0.0);
+        let repetition = Repetition::new(original.clone(), Vec3D::new(3.0, 2.0, 4.0), [2, 1, 3]);
+
+        // Copies sit half a period either side of the original along x, and a whole one along z.
+        for offset in [Vec3D::new(-1.5, 0.0, -4.0), Vec3D::new(1.5, 0.0, 0.0), Vec3D::new(1.5, 0.0, 4.0)] {
+            for point in [Vec3D::zero(), Vec3D::new(0.3, -0.7, 0.2), Vec3D::new(0.0, 0.9, 0.0)] {
+                assert!((repetition.distance(&(point + offset)) - original.distance(&point)).abs() < 1e-5);
+            }
+        }
+
+        // Beyond the last copy the field keeps growing like the original.
+        let beyond = Vec3D::new(10.0, 0.0, 0.0);
+        assert!((repetition.distance(&beyond) - original.distance(&(beyond - Vec3D::new(1.5, 0.0, 0.0)))).abs() < 1e-5);
+    }
+
+    #[test]
+    fn repetition_bounds_cover_every_copy() {
+        let repetition = Repetition::new(sphere(0.0), Vec3D::new(3.0, 2.0, 4.0), [2, 1, 3]);
+        let bounding_box = repetition.bounding_box();
+
+        assert_eq!((bounding_box.x.min, bounding_box.x.max), (-2.5, 2.5));
+        assert_eq!((bounding_box.y.min, bounding_box.y.max), (-1.0, 1.0));
+        assert_eq!((bounding_box.z.min, bounding_box.z.max), (-5.0, 5.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "repetition count must be positive")]
+    fn repetition_needs_copies() {
+        Repetition::new(sphere(0.0), Vec3D::one(), [1, 0, 1]);
+    }
+}
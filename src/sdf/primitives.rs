@@ -0,0 +1,131 @@
+use crate::{vector::Vec3D, aabb::Aabb};
+
+use super::Sdf;
+
+pub struct SphereSdf {
+    pub center: Vec3D,
+    pub radius: f32,
+}
+
+impl Sdf for SphereSdf {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        (*point - self.center).mag() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3D::one() * self.radius;
+        Aabb::from_points(&(self.center - radius), &(self.center + radius))
+    }
+}
+
+/// Axis-aligned box of 'size' around 'center', with its edges and corners
+/// rounded off by 'rounding'.
+pub struct RoundedBoxSdf {
+    pub center: Vec3D,
+    pub size: Vec3D,
+    pub rounding: f32,
+}
+
+impl Sdf for RoundedBoxSdf {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let p = *point - self.center;
+        let q = Vec3D::new(p.x.abs(), p.y.abs(), p.z.abs()) - (self.size / 2.0 - Vec3D::one() * self.rounding);
+
+        let outside = Vec3D::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).mag();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+
+        outside + inside - self.rounding
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&(self.center - self.size / 2.0), &(self.center + self.size / 2.0))
+    }
+}
+
+/// Ring around the vertical axis through 'center', like 'Torus'.
+pub struct TorusSdf {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub tube_radius: f32,
+}
+
+impl Sdf for TorusSdf {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let p = *point - self.center;
+        let ring_distance = (p.x.powi(2) + p.z.powi(2)).sqrt() - self.radius;
+
+        (ring_distance.powi(2) + p.y.powi(2)).sqrt() - self.tube_radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3D::new(self.radius + self.tube_radius, self.tube_radius, self.radius + self.tube_radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// Vertical capsule like 'Capsule', 'height' being the length of its straight part.
+pub struct CapsuleSdf {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl Sdf for CapsuleSdf {
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let p = *point - self.center;
+        let half_height = self.height / 2.0;
+
+        (p - Vec3D::new(0.0, p.y.clamp(-half_height, half_height), 0.0)).mag() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3D::new(self.radius, self.height / 2.0 + self.radius, self.radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+/// The Mandelbulb fractal, scaled so that at the usual 'power' of 8 it
+/// reaches about 'radius' from 'center'.
+pub struct Mandelbulb {
+    pub center: Vec3D,
+    pub radius: f32,
+    pub power: f32,
+    /// More iterations give finer detail and slower rendering.
+    pub iterations: usize,
+}
+
+impl Sdf for Mandelbulb {
+    /// Hubbard and Douady's distance estimate for the escape time set.
+    fn distance(&self, point: &Vec3D) -> f32 {
+        let c = (*point - self.center) / self.radius;
+        let mut z = c;
+        let mut derivative = 1.0;
+        let mut radius = z.mag();
+
+        for _ in 0..self.iterations {
+            if radius > 2.0 {
+                break
+            }
+
+            // Raise 'z' to 'power' in spherical coordinates around the y axis.
+            let theta = (z.y / radius).clamp(-1.0, 1.0).acos() * self.power;
+            let phi = z.z.atan2(z.x) * self.power;
+            derivative = radius.powf(self.power - 1.0) * self.power * derivative + 1.0;
+
+            z = radius.powf(self.power) * Vec3D::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()) + c;
+            radius = z.mag();
+        }
+
+        if radius == 0.0 {
+            return 0.0
+        }
+
+        0.5 * radius.ln() * radius / derivative * self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Lower powers bulge further out than the usual one.
+        let extent = Vec3D::one() * (1.5 * self.radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
@@ -0,0 +1,9 @@
+use crate::vector::Vec3D;
+
+pub mod textures;
+pub mod perlin;
+
+pub trait Texture: Send + Sync {
+    /// Color at surface coordinates ('u', 'v') of 'point'.
+    fn value(&self, u: f32, v: f32, point: &Vec3D) -> Vec3D;
+}
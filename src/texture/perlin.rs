@@ -0,0 +1,185 @@
+use crate::{vector::Vec3D, random};
+
+use super::Texture;
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise with random unit vectors on a lattice.
+pub struct Perlin {
+    vectors: Vec<Vec3D>,
+    permutation_x: Vec<usize>,
+    permutation_y: Vec<usize>,
+    permutation_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new() -> Self {
+        Self {
+            vectors: (0..POINT_COUNT).map(|_| Vec3D::random().unit()).collect(),
+            permutation_x: Self::permutation(),
+            permutation_y: Self::permutation(),
+            permutation_z: Self::permutation(),
+        }
+    }
+
+    /// In [-1, 1].
+    pub fn noise(&self, point: &Vec3D) -> f32 {
+        let (u, v, w) = (point.x - point.x.floor(), point.y - point.y.floor(), point.z - point.z.floor());
+        let (i, j, k) = (point.x.floor() as i64, point.y.floor() as i64, point.z.floor() as i64);
+
+        let mut corners = [[[Vec3D::zero(); 2]; 2]; 2];
+
+        for (di, plane) in corners.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.vectors[
+                        self.permutation_x[((i + di as i64) & 255) as usize]
+                        ^ self.permutation_y[((j + dj as i64) & 255) as usize]
+                        ^ self.permutation_z[((k + dk as i64) & 255) as usize]
+                    ];
+                }
+            }
+        }
+
+        Self::interpolate(&corners, u, v, w)
+    }
+
+    /// Sum of 'depth' octaves of noise with halving amplitude.
+    pub fn turbulence(&self, point: &Vec3D, depth: usize) -> f32 {
+        let mut accumulated = 0.0;
+        let mut point = *point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accumulated += weight * self.noise(&point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+
+        accumulated.abs()
+    }
+
+    fn permutation() -> Vec<usize> {
+        let mut permutation: Vec<usize> = (0..POINT_COUNT).collect();
+
+        for i in (1..POINT_COUNT).rev() {
+            let target = ((random() * (i + 1) as f32) as usize).min(i);
+            permutation.swap(i, target);
+        }
+
+        permutation
+    }
+
+    fn interpolate(corners: &[[[Vec3D; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
+        // Hermite smoothing hides the lattice.
+        let (uu, vv, ww) = (u * u * (3.0 - 2.0 * u), v * v * (3.0 - 2.0 * v), w * w * (3.0 - 2.0 * w));
+        let mut accumulated = 0.0;
+
+        for (i, plane) in corners.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
+                    let (i, j, k) = (i as f32, j as f32, k as f32);
+                    let weight = Vec3D::new(u - i, v - j, w - k);
+
+                    accumulated += (i * uu + (1.0 - i) * (1.0 - uu))
+                        * (j * vv + (1.0 - j) * (1.0 - vv))
+                        * (k * ww + (1.0 - k) * (1.0 - ww))
+                        * corner.dot(&weight);
+                }
+            }
+        }
+
+        accumulated
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoisePattern {
+    /// Smooth noise remapped to [0, 1].
+    Noise,
+    /// Seven octaves of absolute noise.
+    Turbulence,
+    /// Sine stripes along z distorted by turbulence.
+    Marble,
+}
+
+/// Gray procedural texture driven by Perlin noise, tinted by 'color'.
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub pattern: NoisePattern,
+    /// Spatial frequency of the pattern.
+    pub scale: f32,
+    pub color: Vec3D,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: NoisePattern, scale: f32) -> Self {
+        Self { perlin: Perlin::new(), pattern, scale, color: Vec3D::one() }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, point: &Vec3D) -> Vec3D {
+        let point = *point * self.scale;
+
+        let intensity = match self.pattern {
+            NoisePattern::Noise => 0.5 * (1.0 + self.perlin.noise(&point)),
+            NoisePattern::Turbulence => self.perlin.turbulence(&point, 7),
+            NoisePattern::Marble => 0.5 * (1.0 + (point.z + 10.0 * self.perlin.turbulence(&point, 7)).sin()),
+        };
+
+        self.color * intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points on a skewed grid, so they land anywhere within the lattice cells.
+    fn points() -> impl Iterator<Item = Vec3D> {
+        (0..2000).map(|i| Vec3D::new(i as f32 * 0.137 - 50.0, (i % 37) as f32 * 0.291 - 3.0, (i % 11) as f32 * 1.713))
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        let perlin = Perlin::new();
+
+        for point in points() {
+            let noise = perlin.noise(&point);
+            assert!((-1.0..=1.0).contains(&noise), "{} at {:?}", noise, point);
+        }
+    }
+
+    #[test]
+    fn noise_vanishes_on_the_lattice() {
+        let perlin = Perlin::new();
+
+        for point in [Vec3D::zero(), Vec3D::new(3.0, -7.0, 12.0), Vec3D::new(-300.0, 255.0, 256.0)] {
+            assert!(perlin.noise(&point).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous_across_cells() {
+        let perlin = Perlin::new();
+        let step = 1e-4;
+
+        for point in points() {
+            // Moved to the nearest face of its cell along each axis in turn.
+            for axis in [Vec3D::x_unit(), Vec3D::y_unit(), Vec3D::z_unit()] {
+                let along = point.dot(&axis);
+                let face = point + (along.round() - along) * axis;
+
+                let (before, after) = (perlin.noise(&(face - step * axis)), perlin.noise(&(face + step * axis)));
+                assert!((before - after).abs() < 0.002, "jumps from {} to {} at {:?}", before, after, face);
+            }
+        }
+    }
+}
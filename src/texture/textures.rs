@@ -0,0 +1,382 @@
+use std::{fs::{self, File}, io::{self, BufReader}, path::Path, sync::Arc};
+
+use crate::vector::Vec3D;
+
+use super::Texture;
+
+pub struct SolidColor { pub color: Vec3D }
+
+impl SolidColor {
+    pub fn new(color: Vec3D) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _point: &Vec3D) -> Vec3D {
+        self.color
+    }
+}
+
+/// Alternates between two textures in 3D cells of size 'scale'.
+pub struct Checker {
+    pub scale: f32,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, point: &Vec3D) -> Vec3D {
+        let cell = (point.x / self.scale).floor() as i64
+            + (point.y / self.scale).floor() as i64
+            + (point.z / self.scale).floor() as i64;
+
+        if cell % 2 == 0 { self.even.value(u, v, point) } else { self.odd.value(u, v, point) }
+    }
+}
+
+/// Alternates between two textures in a grid over the surface coordinates,
+/// with 'columns' by 'rows' cells per unit of 'u' and 'v'.
+pub struct UvChecker {
+    pub columns: f32,
+    pub rows: f32,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl Texture for UvChecker {
+    fn value(&self, u: f32, v: f32, point: &Vec3D) -> Vec3D {
+        let cell = (u * self.columns).floor() as i64 + (v * self.rows).floor() as i64;
+
+        if cell % 2 == 0 { self.even.value(u, v, point) } else { self.odd.value(u, v, point) }
+    }
+}
+
+/// How texture coordinates outside [0, 1] are mapped back onto the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+/// Bilinearly filtered image. Pixels are stored as linear colors.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3D>,
+    pub wrap: WrapMode,
+}
+
+impl ImageTexture {
+    /// 'pixels' are linear colors, row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3D>) -> Self {
+        assert_eq!(pixels.len(), width * height, "image texture size mismatch");
+
+        Self { width, height, pixels, wrap: WrapMode::default() }
+    }
+
+    /// Loads an sRGB encoded PNG or PPM file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let (width, height, pixels) = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => decode_png(path)?,
+            Some("ppm") => decode_ppm(&fs::read(path)?)?,
+            _ => return Err(invalid_data(&format!("{}: unsupported image format, expected .png or .ppm", path.display()))),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(invalid_data(&format!("{}: image is empty", path.display())))
+        }
+
+        let pixels = pixels.into_iter()
+            .map(|color| Vec3D::new(srgb_decode(color.x), srgb_decode(color.y), srgb_decode(color.z)))
+            .collect();
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vec3D {
+        let wrap = |i: i64, size: usize| -> usize {
+            let size = size as i64;
+
+            match self.wrap {
+                WrapMode::Repeat => i.rem_euclid(size) as usize,
+                WrapMode::Clamp => i.clamp(0, size - 1) as usize,
+                WrapMode::Mirror => {
+                    let i = i.rem_euclid(2 * size);
+                    (if i < size { i } else { 2 * size - 1 - i }) as usize
+                },
+            }
+        };
+
+        self.pixels[wrap(y, self.height) * self.width + wrap(x, self.width)]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: &Vec3D) -> Vec3D {
+        // Images are stored top down while 'v' points up.
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - tx) * self.texel(x0, y0) + tx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * self.texel(x0, y0 + 1) + tx * self.texel(x0 + 1, y0 + 1);
+
+        (1.0 - ty) * top + ty * bottom
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn srgb_decode(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn decode_png(path: &Path) -> io::Result<(usize, usize, Vec<Vec3D>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+
+    let channels = info.color_type.samples();
+    let to_color = |pixel: &[u8]| -> Vec3D {
+        match pixel.len() {
+            1 | 2 => Vec3D::one() * (pixel[0] as f32 / 255.0),
+            _ => Vec3D::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.0,
+        }
+    };
+
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(info.line_size)
+        .flat_map(|line| line[..info.width as usize * channels].chunks_exact(channels).map(to_color))
+        .collect();
+
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+/// Reads plain (P3) or binary (P6) PPM.
+fn decode_ppm(bytes: &[u8]) -> io::Result<(usize, usize, Vec<Vec3D>)> {
+    let mut position = 0;
+
+    // Header tokens are separated by whitespace and may be followed by '#' comments.
+    let mut token = || -> io::Result<&[u8]> {
+        loop {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+
+            if position < bytes.len() && bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+            } else {
+                break
+            }
+        }
+
+        let start = position;
+        while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+
+        match &bytes[start..position] {
+            [] => Err(invalid_data("unexpected end of PPM data")),
+            token => Ok(token),
+        }
+    };
+
+    let magic = token()?.to_vec();
+    let mut number = || -> io::Result<usize> {
+        let token = token()?;
+        std::str::from_utf8(token).ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data(&format!("invalid PPM number '{}'", String::from_utf8_lossy(token))))
+    };
+
+    let (width, height, max_value) = (number()?, number()?, number()?);
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data("PPM maximum value must be between 1 and 65535"))
+    }
+
+    let count = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| invalid_data("PPM image is too large"))?;
+    let samples: Vec<usize> = match magic.as_slice() {
+        b"P3" => (0..count).map(|_| number()).collect::<io::Result<_>>()?,
+        b"P6" => {
+            // A single whitespace byte separates the header from the raster.
+            let raster = bytes.get(position + 1..).unwrap_or(&[]);
+            let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+
+            if raster.len() / bytes_per_sample < count {
+                return Err(invalid_data("unexpected end of PPM data"))
+            }
+
+            if bytes_per_sample == 1 {
+                raster[..count].iter().map(|&b| b as usize).collect()
+            } else {
+                raster[..count * 2].chunks_exact(2).map(|b| ((b[0] as usize) << 8) | b[1] as usize).collect()
+            }
+        },
+        _ => return Err(invalid_data("not a P3 or P6 PPM file")),
+    };
+
+    let pixels = samples.chunks_exact(3)
+        .map(|rgb| Vec3D::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / max_value as f32)
+        .collect();
+
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grey(value: f32) -> Vec3D {
+        Vec3D::one() * value
+    }
+
+    /// One row of a black and a white texel.
+    fn black_to_white(wrap: WrapMode) -> ImageTexture {
+        ImageTexture::new(2, 1, vec![grey(0.0), grey(1.0)]).with_wrap(wrap)
+    }
+
+    fn assert_grey(color: Vec3D, expected: f32) {
+        assert!((color - grey(expected)).mag() < 1e-5, "{:?} is not {}", color, expected);
+    }
+
+    #[test]
+    fn image_texture_filters_between_texels() {
+        let texture = black_to_white(WrapMode::Clamp);
+        let value = |u: f32| texture.value(u, 0.5, &Vec3D::zero());
+
+        // Texel centers lie at a quarter and three quarters of the width.
+        assert_grey(value(0.25), 0.0);
+        assert_grey(value(0.75), 1.0);
+        assert_grey(value(0.5), 0.5);
+        assert_grey(value(0.375), 0.25);
+    }
+
+    #[test]
+    fn image_texture_rows_run_top_down() {
+        let texture = ImageTexture::new(1, 2, vec![grey(1.0), grey(0.0)]).with_wrap(WrapMode::Clamp);
+
+        assert_grey(texture.value(0.5, 0.75, &Vec3D::zero()), 1.0);
+        assert_grey(texture.value(0.5, 0.25, &Vec3D::zero()), 0.0);
+    }
+
+    #[test]
+    fn image_texture_wraps() {
+        let value = |wrap: WrapMode, u: f32| black_to_white(wrap).value(u, 0.5, &Vec3D::zero());
+
+        // Repeating filters across the edge into the other side of the image.
+        assert_grey(value(WrapMode::Repeat, -0.25), 1.0);
+        assert_grey(value(WrapMode::Repeat, 1.25), 0.0);
+        assert_grey(value(WrapMode::Repeat, 1.0), 0.5);
+
+        assert_grey(value(WrapMode::Clamp, -3.0), 0.0);
+        assert_grey(value(WrapMode::Clamp, 1.0), 1.0);
+        assert_grey(value(WrapMode::Clamp, 2.5), 1.0);
+
+        // Mirroring repeats the edge texel, then runs back through the image.
+        assert_grey(value(WrapMode::Mirror, -0.25), 0.0);
+        assert_grey(value(WrapMode::Mirror, 1.25), 1.0);
+        assert_grey(value(WrapMode::Mirror, 1.75), 0.0);
+        assert_grey(value(WrapMode::Mirror, 2.25), 0.0);
+    }
+
+    fn checker_colors() -> (Arc<dyn Texture>, Arc<dyn Texture>) {
+        (Arc::new(SolidColor::new(grey(0.0))), Arc::new(SolidColor::new(grey(1.0))))
+    }
+
+    #[test]
+    fn checker_alternates_across_negative_cells() {
+        let (even, odd) = checker_colors();
+        let checker = Checker { scale: 2.0, even, odd };
+        let value = |x: f32, y: f32, z: f32| checker.value(0.0, 0.0, &Vec3D::new(x, y, z));
+
+        assert_grey(value(1.0, 1.0, 1.0), 0.0);
+        assert_grey(value(-1.0, 1.0, 1.0), 1.0);
+        assert_grey(value(-3.0, 1.0, 1.0), 0.0);
+        assert_grey(value(-1.0, -1.0, 1.0), 0.0);
+        assert_grey(value(-1.0, -1.0, -1.0), 1.0);
+    }
+
+    #[test]
+    fn uv_checker_counts_cells_per_unit() {
+        let (even, odd) = checker_colors();
+        let checker = UvChecker { columns: 4.0, rows: 2.0, even, odd };
+        let value = |u: f32, v: f32| checker.value(u, v, &Vec3D::zero());
+
+        assert_grey(value(0.1, 0.1), 0.0);
+        assert_grey(value(0.3, 0.1), 1.0);
+        assert_grey(value(0.3, 0.6), 0.0);
+        assert_grey(value(0.99, 0.6), 0.0);
+        assert_grey(value(0.99, 0.4), 1.0);
+    }
+
+    #[test]
+    fn decodes_plain_ppm_with_comments() {
+        let (width, height, pixels) = decode_ppm(b"P3 # comment\n2 1\n# another\n255\n255 0 0  0 51 255\n").unwrap();
+
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, vec![Vec3D::new(1.0, 0.0, 0.0), Vec3D::new(0.0, 0.2, 1.0)]);
+    }
+
+    #[test]
+    fn decodes_binary_ppm() {
+        let (_, _, pixels) = decode_ppm(b"P6\n1 1\n255\n\xff\x00\x33").unwrap();
+        assert_eq!(pixels, vec![Vec3D::new(1.0, 0.0, 0.2)]);
+
+        let (_, _, pixels) = decode_ppm(b"P6\n1 1\n65535\n\xff\xff\x00\x00\x00\x00").unwrap();
+        assert_eq!(pixels, vec![Vec3D::new(1.0, 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn rejects_malformed_ppm_headers() {
+        for bytes in [&b"P5\n1 1\n255\n\0"[..], b"P6\n1\n", b"P6\n-1 1\n255\n\0\0\0", b"P6\n1 1\n0\n\0\0\0", b"P6\n1 1\n65536\n\0\0\0\0\0\0"] {
+            let error = decode_ppm(bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", String::from_utf8_lossy(bytes));
+        }
+    }
+
+    #[test]
+    fn rejects_ppm_sizes_overflowing_the_count() {
+        let bytes = format!("P6\n{} {}\n255\n\0\0\0", usize::MAX / 2, 2);
+
+        assert!(decode_ppm(bytes.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_ppm_data() {
+        assert!(decode_ppm(b"P6\n2 1\n255\n\0\0\0\0\0").is_err());
+        assert!(decode_ppm(b"P3\n2 1\n255\n0 0 0 0 0").is_err());
+    }
+}
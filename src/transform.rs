@@ -0,0 +1,188 @@
+use crate::{vector::Vec3D, ray::Ray, aabb::Aabb, degrees_to_radians};
+
+/// Row major, applied to column vectors.
+pub type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Affine transformation, kept together with its inverse so neither has to
+/// be computed while rendering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    pub const fn identity() -> Self {
+        Self { matrix: IDENTITY, inverse: IDENTITY }
+    }
+
+    pub fn translation(offset: &Vec3D) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+
+        for (i, offset) in [offset.x, offset.y, offset.z].into_iter().enumerate() {
+            matrix[i][3] = offset;
+            inverse[i][3] = -offset;
+        }
+
+        Self { matrix, inverse }
+    }
+
+    /// Scales along each axis by the matching component of 'factors', none of
+    /// which may be zero.
+    pub fn scaling(factors: &Vec3D) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+
+        for (i, factor) in [factors.x, factors.y, factors.z].into_iter().enumerate() {
+            matrix[i][i] = factor;
+            inverse[i][i] = 1.0 / factor;
+        }
+
+        Self { matrix, inverse }
+    }
+
+    /// Rotates counterclockwise by 'degrees' around 'axis', looking down
+    /// the axis towards the origin.
+    pub fn rotation(axis: &Vec3D, degrees: f32) -> Self {
+        let Vec3D { x, y, z } = axis.unit();
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        let k = 1.0 - cos;
+
+        // Rodrigues' rotation formula.
+        let matrix = [
+            [cos + x * x * k, x * y * k - z * sin, x * z * k + y * sin, 0.0],
+            [y * x * k + z * sin, cos + y * y * k, y * z * k - x * sin, 0.0],
+            [z * x * k - y * sin, z * y * k + x * sin, cos + z * z * k, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        // Rotations are orthogonal, so the transpose undoes them.
+        Self { matrix, inverse: transpose(&matrix) }
+    }
+
+    /// Applies 'self' and then 'next'.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self { matrix: multiply(&next.matrix, &self.matrix), inverse: multiply(&self.inverse, &next.inverse) }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self { matrix: self.inverse, inverse: self.matrix }
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    pub fn point(&self, point: &Vec3D) -> Vec3D {
+        self.vector(point) + Vec3D::new(self.matrix[0][3], self.matrix[1][3], self.matrix[2][3])
+    }
+
+    /// Transforms a direction or offset, which translation does not affect.
+    pub fn vector(&self, vector: &Vec3D) -> Vec3D {
+        let row = |i: usize| self.matrix[i][0] * vector.x + self.matrix[i][1] * vector.y + self.matrix[i][2] * vector.z;
+
+        Vec3D::new(row(0), row(1), row(2))
+    }
+
+    /// Transforms the origin and direction of 'ray'. The direction is not
+    /// normalized, so the ray parameter is the same in both spaces.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::new(&self.point(&ray.origin), &self.vector(&ray.direction)).with_time(ray.time)
+    }
+
+    /// Transforms a surface normal with the inverse transpose, so it stays
+    /// perpendicular to the transformed surface. The result is not normalized.
+    pub fn normal(&self, normal: &Vec3D) -> Vec3D {
+        let column = |i: usize| self.inverse[0][i] * normal.x + self.inverse[1][i] * normal.y + self.inverse[2][i] * normal.z;
+
+        Vec3D::new(column(0), column(1), column(2))
+    }
+
+    /// Factor by which the transform scales volumes, negative where it mirrors.
+    pub fn determinant(&self) -> f32 {
+        let m = &self.matrix;
+
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Box enclosing the transformed corners of 'aabb'. Must be bounded.
+    pub fn bounding_box(&self, aabb: &Aabb) -> Aabb {
+        let mut min = Vec3D::one() * f32::INFINITY;
+        let mut max = Vec3D::one() * f32::NEG_INFINITY;
+
+        for corner in 0..8 {
+            let x = if corner & 1 == 0 { aabb.x.min } else { aabb.x.max };
+            let y = if corner & 2 == 0 { aabb.y.min } else { aabb.y.max };
+            let z = if corner & 4 == 0 { aabb.z.min } else { aabb.z.max };
+            let point = self.point(&Vec3D::new(x, y, z));
+
+            min = Vec3D::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+            max = Vec3D::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+        }
+
+        Aabb::from_points(&min, &max)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+
+    product
+}
+
+fn transpose(matrix: &Matrix) -> Matrix {
+    let mut transposed = *matrix;
+
+    for (i, row) in transposed.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = matrix[j][i];
+        }
+    }
+
+    transposed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_keep_their_parameter() {
+        let transform = Transform::scaling(&Vec3D::new(2.0, 0.5, 1.0))
+            .then(&Transform::rotation(&Vec3D::y_unit(), 30.0))
+            .then(&Transform::translation(&Vec3D::new(1.0, -2.0, 3.0)));
+        let ray = Ray::new(&Vec3D::new(0.5, 1.0, -1.0), &Vec3D::new(1.0, 2.0, 0.5)).with_time(0.25);
+
+        let transformed = transform.ray(&ray);
+        assert_eq!(transformed.time, 0.25);
+
+        for t in [0.0, 0.5, 3.0] {
+            assert!((transformed.at(t) - transform.point(&ray.at(t))).mag() < 1e-5);
+        }
+
+        let back = transform.inverse().ray(&transformed);
+        assert!((back.direction - ray.direction).mag() < 1e-5);
+    }
+}
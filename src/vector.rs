@@ -1,7 +1,5 @@
 use std::ops::Neg;
 
-use macroquad::math::Vec3;
-
 use crate::{random, random_neg_pos};
 
 mod operators;
@@ -81,6 +79,32 @@ impl Vec3D {
         }
     }
 
+    /// Unit vector on the hemisphere around the unit vector 'normal', with
+    /// density proportional to the cosine between them.
+    #[inline]
+    pub fn random_cosine_direction(normal: &Vec3D) -> Vec3D {
+        let phi = 2.0 * std::f32::consts::PI * random();
+        let r2 = random();
+        let sin_theta = r2.sqrt();
+
+        let (u, v) = normal.orthonormal_basis();
+
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + (1.0 - r2).sqrt() * *normal
+    }
+
+    /// Unit vector uniformly distributed over the directions within the angle
+    /// whose cosine is 'cos_theta_max' of the unit vector 'axis'.
+    #[inline]
+    pub fn random_in_cone(axis: &Vec3D, cos_theta_max: f32) -> Vec3D {
+        let z = 1.0 + random() * (cos_theta_max - 1.0);
+        let phi = 2.0 * std::f32::consts::PI * random();
+        let sin_theta = (1.0 - z.powi(2)).max(0.0).sqrt();
+
+        let (u, v) = axis.orthonormal_basis();
+
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + z * *axis
+    }
+
     #[inline]
     pub fn is_near_zero(&self) -> bool {
         (self.x.abs() <= f32::EPSILON) && (self.y.abs() <= f32::EPSILON) && (self.z.abs() <= f32::EPSILON)
@@ -101,6 +125,17 @@ impl Vec3D {
         *self / self.mag()
     }
 
+    /// Relative luminance of a linear sRGB color.
+    #[inline]
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    #[inline]
+    pub fn max_component(&self) -> f32 {
+        self.x.max(self.y).max(self.z)
+    }
+
     #[inline]
     pub fn dot(&self, vector: &Self) -> f32 {
         (self.x * vector.x) + (self.y * vector.y) + (self.z * vector.z)
@@ -153,4 +188,18 @@ impl Vec3D {
         r_out_perp + r_out_parallel
     }
 
+    // self must be a unit vector; returns two unit vectors completing a right-handed basis
+    #[inline]
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let sign = 1.0f32.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Self::new(1.0 + sign * self.x.powi(2) * a, sign * b, -sign * self.x),
+            Self::new(b, sign + self.y.powi(2) * a, -self.y),
+        )
+    }
+
 }
\ No newline at end of file
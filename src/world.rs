@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::{
+    vector::Vec3D,
+    body::{Body, body_list::BodyList, medium::Fog},
+    environment::{Environment, environments::UniformEnvironment},
+    light::LightSource,
+    material::Material,
+};
+
+const BACKGROUND_COLOR: Vec3D = Vec3D::new(0.1, 0.2, 0.7);
+
+/// Everything rays are traced against.
+pub struct World {
+    pub bodies: Arc<dyn Body>,
+    /// Emissive bodies among 'bodies', sampled directly at every diffuse hit.
+    pub lights: BodyList,
+    /// Lights without a body, all sampled at every diffuse hit.
+    pub light_sources: Vec<Arc<dyn LightSource>>,
+    /// Seen by rays leaving the scene. Sampled as a light where it supports it.
+    pub environment: Arc<dyn Environment>,
+}
+
+impl World {
+    /// Collects the lights of 'bodies'.
+    pub fn new(bodies: Arc<dyn Body>) -> Self {
+        let mut lights = BodyList::new();
+        bodies.clone().collect_lights(&mut lights);
+
+        Self {
+            bodies,
+            lights,
+            light_sources: vec![],
+            environment: Arc::new(UniformEnvironment::new(BACKGROUND_COLOR)),
+        }
+    }
+
+    pub fn with_light_sources(mut self, light_sources: Vec<Arc<dyn LightSource>>) -> Self {
+        self.light_sources = light_sources;
+        self
+    }
+
+    pub fn with_environment(mut self, environment: Arc<dyn Environment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Fills the space between the bodies with fog of 'density' scattering
+    /// light by 'phase'.
+    pub fn with_fog(mut self, density: f32, phase: Arc<dyn Material>) -> Self {
+        self.bodies = Arc::new(Fog { bodies: self.bodies, density, phase });
+        self
+    }
+}